@@ -0,0 +1,190 @@
+use crate::maze::Maze;
+use crate::topology::rectangular::{DOWN, LEFT, RIGHT, UP};
+use crate::topology::{CellId, Direction, Rectangular};
+
+/// Plain-text mazes for square grids. Cells are two characters wide between `+` corners,
+/// walls are `--` and `|`, carved walls are blank. The entrance and exit are outer walls marked
+/// `E` and `X`, or `*` when both are the same wall. A cell a tunnel runs under holds `||` when
+/// the passage on top runs up and down, or `==` when it runs across.
+///
+/// ```text
+/// +--+--+
+/// E     |
+/// +--+  +
+/// |     X
+/// +--+--+
+/// ```
+pub fn export(maze: &Maze) -> Result<String, String> {
+    let [rows, columns] = maze.topology().grid().ok_or_else(|| String::from("Only rectangular mazes that do not wrap around can be written as ASCII art"))?;
+    let cell = |row: u32, column: u32| row as usize * columns as usize + column as usize;
+    let opening = |cell: CellId, direction: Direction| {
+        match (maze.entrance() == Some((cell, direction)), maze.exit() == Some((cell, direction))) {
+            (true, true) => Some('*'),
+            (true, false) => Some('E'),
+            (false, true) => Some('X'),
+            (false, false) => None
+        }
+    };
+    let horizontal = |cell: CellId, direction: Direction| match opening(cell, direction) {
+        Some(mark) => format!("{} ", mark),
+        None if maze.has_wall(cell, direction) => String::from("--"),
+        None => String::from("  ")
+    };
+    let vertical = |cell: CellId, direction: Direction| match opening(cell, direction) {
+        Some(mark) => mark,
+        None if maze.has_wall(cell, direction) => '|',
+        None => ' '
+    };
+
+    let mut text = String::new();
+    for row in 0..rows {
+        text.push('+');
+        for column in 0..columns {
+            text.push_str(&horizontal(cell(row, column), UP));
+            text.push('+');
+        }
+        text.push('\n');
+        text.push(vertical(cell(row, 0), LEFT));
+        for column in 0..columns {
+            text.push_str(match maze.crossing(cell(row, column)) {
+                Some(UP) => "==",
+                Some(_) => "||",
+                None => "  "
+            });
+            text.push(vertical(cell(row, column), RIGHT));
+        }
+        text.push('\n');
+    }
+    text.push('+');
+    for column in 0..columns {
+        text.push_str(&horizontal(cell(rows - 1, column), DOWN));
+        text.push('+');
+    }
+    text.push('\n');
+    Ok(text)
+}
+
+/// Reads a maze written by `export` back into a plain rectangular grid. Lines may lose their
+/// trailing blanks along the way.
+pub fn import(text: &str) -> Result<Maze, String> {
+    let lines = text.lines().map(|line| line.trim_end().chars().collect::<Vec<char>>()).collect::<Vec<Vec<char>>>();
+    let lines = &lines[lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len())..];
+    let lines = &lines[..lines.iter().rposition(|line| !line.is_empty()).map_or(0, |last| last + 1)];
+    if lines.len() < 3 || lines.len() % 2 == 0 || lines[0].first() != Some(&'+') {
+        return Err(String::from("ASCII art must be an odd number of lines, at least three, starting with a row of + corners"));
+    }
+    let columns = lines[0].iter().filter(|symbol| **symbol == '+').count() - 1;
+    let rows = lines.len() / 2;
+    if columns == 0 || lines[0].len() != columns * 3 + 1 {
+        return Err(String::from("The first line must be + corners with two characters between each"));
+    }
+    let at = |line: usize, position: usize| lines[line].get(position).copied().unwrap_or(' ');
+
+    let mut maze = Maze::new(Box::new(Rectangular::new(rows as u32, columns as u32)));
+    let mut entrance = None;
+    let mut exit = None;
+    let mut mark = |symbol: char, wall: (CellId, Direction), outer: bool, line: usize| -> Result<bool, String> {
+        match symbol {
+            'E' | 'X' | '*' if !outer => Err(format!("Line {}: only outer walls can be marked {}", line + 1, symbol)),
+            'E' | 'X' | '*' => {
+                let openings = match symbol {
+                    'E' => vec![&mut entrance],
+                    'X' => vec![&mut exit],
+                    _ => vec![&mut entrance, &mut exit]
+                };
+                for opening in openings {
+                    if opening.replace(wall).is_some() {
+                        return Err(format!("Line {}: the maze has more than one {}", line + 1, if symbol == 'X' { "exit" } else { "entrance" }));
+                    }
+                }
+                Ok(false)
+            },
+            ' ' if outer => Err(format!("Line {}: an open outer wall needs an E or X mark", line + 1)),
+            ' ' => Ok(true),
+            _ => Ok(false)
+        }
+    };
+
+    for row in 0..=rows {
+        let line = row * 2;
+        for column in 0..columns {
+            let position = column * 3;
+            if at(line, position) != '+' || at(line, position + 3) != '+' {
+                return Err(format!("Line {}: expected + at every third character", line + 1));
+            }
+            let segment = [at(line, position + 1), at(line, position + 2)];
+            let (cell, direction) = if row < rows { (row * columns + column, UP) } else { ((rows - 1) * columns + column, DOWN) };
+            let symbol = match segment {
+                ['-', '-'] => '-',
+                [' ', ' '] => ' ',
+                [mark, ' '] if "EX*".contains(mark) => mark,
+                _ => return Err(format!("Line {}: expected --, blanks, or a marked opening between corners", line + 1))
+            };
+            if mark(symbol, (cell, direction), row == 0 || row == rows, line)? {
+                maze.carve(cell, direction);
+            }
+        }
+        if row == rows {
+            break;
+        }
+        let line = line + 1;
+        for column in 0..=columns {
+            let position = column * 3;
+            let (cell, direction) = if column < columns { (row * columns + column, LEFT) } else { (row * columns + columns - 1, RIGHT) };
+            let symbol = at(line, position);
+            if !"| EX*".contains(symbol) {
+                return Err(format!("Line {}: unexpected '{}' where a wall belongs", line + 1, symbol));
+            }
+            if mark(symbol, (cell, direction), column == 0 || column == columns, line)? {
+                maze.carve(cell, direction);
+            }
+            if column < columns {
+                match [at(line, position + 1), at(line, position + 2)] {
+                    [' ', ' '] => (),
+                    ['=', '='] => maze.set_crossing(cell, UP),
+                    ['|', '|'] => maze.set_crossing(cell, RIGHT),
+                    _ => return Err(format!("Line {}: a cell must hold blanks, || or ==", line + 1))
+                }
+            }
+        }
+    }
+    match (entrance, exit) {
+        (Some(entrance), Some(exit)) => maze.set_openings(entrance, exit),
+        (None, None) => (),
+        _ => return Err(String::from("A maze needs both an entrance and an exit, or neither"))
+    }
+    Ok(maze)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::{assert_same_maze, generated};
+
+    #[test]
+    fn round_trips() {
+        for settings in ["table_size:7x9", "table_size:12x12\nweave:true", "table_size:1x1", "table_size:5x8\nentrance:random\nexit:random"] {
+            for seed in 0..4 {
+                let maze = generated(settings, seed);
+                let text = export(&maze).unwrap();
+                assert_same_maze(&import(&text).unwrap(), &maze);
+                assert_eq!(export(&import(&text).unwrap()).unwrap(), text);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_wrapped_grids() {
+        for wrap in ["cylinder", "torus", "mobius", "klein"] {
+            let maze = generated(&format!("table_size:6x6\nwrap:{}", wrap), 0);
+            assert!(export(&maze).is_err(), "{}", wrap);
+        }
+    }
+
+    #[test]
+    fn rejects_unmarked_openings() {
+        assert!(import("+--+\n   |\n+--+\n").is_err());
+        assert!(import("+--+\nE  E\n+--+\n").is_err());
+        assert!(import("+--+\n|EE|\n+--+\n").is_err());
+    }
+}
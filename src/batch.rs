@@ -0,0 +1,340 @@
+use crate::formats::{self, Format};
+use crate::generator::{self, Backtracker, Step};
+use crate::maze::{Maze, Provenance};
+use crate::render::{self, Style};
+use crate::settings::Settings;
+use crate::solver;
+use crate::topology::{Topology, TopologyKind};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The seed of maze number `index` in a batch, mixed from the master seed with SplitMix64 so
+/// neighbouring indices get unrelated streams.
+pub fn maze_seed(master_seed: u64, index: u64) -> u64 {
+    let mut mixed = master_seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    mixed ^ (mixed >> 31)
+}
+
+/// Generates one finished maze, openings included. The same settings and seed always give the
+/// same maze.
+pub fn generate(settings: &Settings, seed: u64) -> Result<Maze, String> {
+    generate_observed(settings, seed, |_, _, _| ())
+}
+
+/// Generates a maze the way `generate` does, calling `observe` after every step with the maze
+/// so far, the generator and the step it took. The openings are made after the last step.
+pub fn generate_observed(settings: &Settings, seed: u64, mut observe: impl FnMut(&Maze, &Backtracker, &Step)) -> Result<Maze, String> {
+    let mut generation = Generation::new(settings, seed)?;
+    while !generation.is_finished() {
+        let step = generation.step();
+        observe(generation.maze(), generation.backtracker(), &step);
+    }
+    Ok(generation.finish())
+}
+
+/// The maze `generate` makes from some settings and a seed, generated one step at a time by
+/// whoever holds it.
+pub struct Generation {
+    maze: Maze,
+    backtracker: Backtracker,
+    rng: StdRng,
+    settings: Settings,
+    seed: u64
+}
+
+impl Generation {
+    pub fn new(settings: &Settings, seed: u64) -> Result<Self, String> {
+        Self::on(settings.build_topology()?, settings, seed)
+    }
+
+    /// Generates on a topology already built from the settings, so a batch reads a mask from
+    /// disk only once.
+    pub fn on(topology: Box<dyn Topology>, settings: &Settings, seed: u64) -> Result<Self, String> {
+        let maze = Maze::new(topology);
+        settings.entrance.check(maze.topology()).map_err(|error| format!("Invalid entrance: {}", error))?;
+        settings.exit.check(maze.topology()).map_err(|error| format!("Invalid exit: {}", error))?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let start = generator::random_start(maze.topology(), &mut rng);
+        let backtracker = Backtracker::new(&maze, start, settings.weave);
+        Ok(Self {
+            maze,
+            backtracker,
+            rng,
+            settings: settings.clone(),
+            seed
+        })
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn backtracker(&self) -> &Backtracker {
+        &self.backtracker
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.backtracker.is_finished()
+    }
+
+    pub fn step(&mut self) -> Step {
+        self.backtracker.step(&mut self.maze, &mut self.rng)
+    }
+
+    /// Takes any steps left, then cuts the entrance and exit.
+    pub fn finish(mut self) -> Maze {
+        while !self.is_finished() {
+            self.step();
+        }
+        self.maze.make_openings(self.settings.entrance, self.settings.exit, &mut self.rng);
+        self.maze.set_provenance(Provenance {
+            generator: String::from(generator::name(self.settings.weave)),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            seed: Some(self.seed)
+        });
+        self.maze
+    }
+}
+
+/// Generates `count` mazes on `threads` worker threads. Maze `index` is always generated from
+/// `maze_seed(master_seed, index)` and lands at that index, so the result does not depend on
+/// the number of threads or how the work was shared out.
+pub fn generate_batch(settings: &Settings, master_seed: u64, count: usize, threads: usize) -> Result<Vec<Maze>, String> {
+    let topology = settings.build_topology()?;
+    run_parallel(count, threads, |index| Ok(Generation::on(topology.clone(), settings, maze_seed(master_seed, index as u64))?.finish())).into_iter().collect()
+}
+
+/// A hash of the carved walls that stays the same across platforms and Rust releases, to
+/// compare the mazes of two batches by: 64-bit FNV-1a over `BitSet::to_bytes`.
+pub fn fingerprint(maze: &Maze) -> u64 {
+    maze.passages().to_bytes().iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Runs `work` for every index below `count` on `threads` worker threads, each taking the
+/// next index as soon as it is free, and returns the results in index order.
+fn run_parallel<T: Send>(count: usize, threads: usize, work: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<T>>>());
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                if index >= count {
+                    break;
+                }
+                let result = work(index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect()
+}
+
+/// Numbers that tell finished mazes apart, counted over the cells the topology includes.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Metrics {
+    pub cells: usize,
+    /// Cells with a single way in or out.
+    pub dead_ends: usize,
+    /// Cells where three or more ways meet.
+    pub junctions: usize,
+    /// Cells a tunnel runs under.
+    pub crossings: usize,
+    /// Cells on the way from the entrance to the exit, both included, or `None` while the maze
+    /// has no openings.
+    pub solution_length: Option<usize>
+}
+
+impl Metrics {
+    pub fn of(maze: &Maze) -> Self {
+        let topology = maze.topology();
+        let cells = (0..topology.cell_count()).filter(|cell| topology.is_included(*cell)).collect::<Vec<usize>>();
+        let ways = |cell: usize| maze.links(cell).len();
+        Self {
+            cells: cells.len(),
+            dead_ends: cells.iter().filter(|cell| ways(**cell) == 1).count(),
+            junctions: cells.iter().filter(|cell| ways(**cell) >= 3).count(),
+            crossings: cells.iter().filter(|cell| maze.crossing(**cell).is_some()).count(),
+            solution_length: solver::solve(maze).map(|path| path.len())
+        }
+    }
+}
+
+/// A maze written by `write_dataset`, the way the manifest lists it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub file: String,
+    pub seed: u64,
+    pub fingerprint: u64,
+    pub algorithm: String,
+    pub topology: TopologyKind,
+    pub rows: u32,
+    pub columns: u32,
+    pub floors: u8,
+    #[serde(flatten)]
+    pub metrics: Metrics
+}
+
+/// Generates `count` mazes from each of the `variants` and writes them into `directory` in
+/// `format`, named `maze_<index>` with the index running on across the variants. Maze `index`
+/// is generated from `maze_seed(master_seed, index)`, so the files do not depend on the number
+/// of threads. Returns what the manifest should list, in file order.
+pub fn write_dataset(variants: &[Settings], count: usize, master_seed: u64, threads: usize, directory: &str, format: Format) -> Result<Vec<Entry>, String> {
+    fs::create_dir_all(directory).map_err(|error| format!("Error creating the directory {}: {}", directory, error))?;
+    let topologies = variants.iter().map(Settings::build_topology).collect::<Result<Vec<Box<dyn Topology>>, String>>()?;
+    let total = variants.len() * count;
+    let width = total.saturating_sub(1).to_string().len();
+    run_parallel(total, threads, |index| {
+        let settings = &variants[index / count];
+        let seed = maze_seed(master_seed, index as u64);
+        let file = format!("maze_{:0width$}.{}", index, format.extension(), width = width);
+        let path = Path::new(directory).join(&file);
+        let path = path.to_str().ok_or_else(|| format!("The path {} is not valid UTF-8", path.display()))?;
+        let generation = Generation::on(topologies[index / count].clone(), settings, seed)?;
+        let maze = match format {
+            Format::Gif => render::save_generation_gif(generation, path)?,
+            _ => {
+                let maze = generation.finish();
+                formats::save(&formats::to_bytes(&maze, format, &Style::from_settings(settings))?, path)?;
+                maze
+            }
+        };
+        let layout = maze.topology().layout();
+        Ok(Entry {
+            file,
+            seed,
+            fingerprint: fingerprint(&maze),
+            algorithm: String::from(generator::name(settings.weave)),
+            topology: layout.kind,
+            rows: layout.rows,
+            columns: layout.columns,
+            floors: layout.floors,
+            metrics: Metrics::of(&maze)
+        })
+    })
+    .into_iter()
+    .collect()
+}
+
+/// The manifest as CSV with a header row. A maze without openings has an empty
+/// solution_length.
+pub fn manifest_csv(entries: &[Entry]) -> String {
+    let mut text = String::from("file,seed,fingerprint,algorithm,topology,rows,columns,floors,cells,dead_ends,junctions,crossings,solution_length\n");
+    for entry in entries {
+        let metrics = &entry.metrics;
+        text.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            entry.file,
+            entry.seed,
+            entry.fingerprint,
+            entry.algorithm,
+            entry.topology,
+            entry.rows,
+            entry.columns,
+            entry.floors,
+            metrics.cells,
+            metrics.dead_ends,
+            metrics.junctions,
+            metrics.crossings,
+            metrics.solution_length.map_or_else(String::new, |length| length.to_string())
+        ));
+    }
+    text
+}
+
+/// The manifest as a JSON array with one object per maze, holding the same fields as the CSV.
+pub fn manifest_json(entries: &[Entry]) -> String {
+    serde_json::to_string_pretty(entries).expect("Manifests always serialize") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::assert_same_maze;
+    use crate::topology::rectangular::{DOWN, LEFT, RIGHT};
+    use crate::topology::Rectangular;
+    use std::env;
+
+    #[test]
+    fn batches_do_not_depend_on_the_thread_count() {
+        for text in ["table_size:9x11", "weave:true\ntable_size:8x8\nentrance:random\nexit:random", "topology:hexagonal\nfloors:2\ntable_size:4x6"] {
+            let settings = text.parse::<Settings>().unwrap();
+            let single = generate_batch(&settings, 42, 12, 1).unwrap();
+            for threads in [2, 5, 16] {
+                let parallel = generate_batch(&settings, 42, 12, threads).unwrap();
+                assert_eq!(parallel.len(), single.len());
+                for (index, (maze, other)) in single.iter().zip(&parallel).enumerate() {
+                    assert_same_maze(maze, other);
+                    assert_eq!(maze.provenance(), other.provenance());
+                    assert_same_maze(maze, &generate(&settings, maze_seed(42, index as u64)).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fingerprints_are_fixed() {
+        // FNV-1a of the packed walls [9, 73, 0], worked out by hand.
+        let mut maze = Maze::new(Box::new(Rectangular::new(3, 3)));
+        for (cell, direction) in [(0, RIGHT), (1, DOWN), (4, RIGHT), (5, DOWN), (8, LEFT)] {
+            maze.carve(cell, direction);
+        }
+        assert_eq!(maze.passages().to_bytes(), [9, 73, 0]);
+        assert_eq!(fingerprint(&maze), 0x1534_8518_8dc8_5895);
+    }
+
+    #[test]
+    fn writes_datasets_with_a_manifest() {
+        let directory = env::temp_dir().join(format!("maze_maker_test_dataset_{}", std::process::id()));
+        let variants = ["table_size:5x5", "table_size:4x6\nweave:true"].map(|text| text.parse::<Settings>().unwrap());
+        let entries = write_dataset(&variants, 6, 7, 3, directory.to_str().unwrap(), Format::Json).unwrap();
+
+        let files = entries.iter().map(|entry| entry.file.as_str()).collect::<Vec<&str>>();
+        assert_eq!(files, (0..12).map(|index| format!("maze_{:02}.json", index)).collect::<Vec<String>>());
+        for (index, entry) in entries.iter().enumerate() {
+            let settings = &variants[index / 6];
+            let maze = formats::load(directory.join(&entry.file).to_str().unwrap()).unwrap();
+            assert_eq!(entry.seed, maze_seed(7, index as u64));
+            assert_eq!(entry.fingerprint, fingerprint(&maze));
+            assert_eq!(entry.fingerprint, fingerprint(&generate(settings, entry.seed).unwrap()));
+            assert_eq!(entry.algorithm, generator::name(settings.weave));
+            assert_eq!((entry.rows, entry.columns), (settings.table[0], settings.table[1]));
+            assert_eq!(entry.metrics, Metrics::of(&maze));
+        }
+
+        let csv = manifest_csv(&entries);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("file,seed,fingerprint,algorithm,topology,rows,columns,floors,cells,dead_ends,junctions,crossings,solution_length"));
+        for (line, entry) in lines.by_ref().zip(&entries) {
+            let fields = line.split(',').collect::<Vec<&str>>();
+            assert_eq!(fields.len(), 13);
+            assert_eq!(fields[..3], [entry.file.clone(), entry.seed.to_string(), entry.fingerprint.to_string()]);
+            assert_eq!(fields[3..8], [entry.algorithm.as_str(), "rectangular", &entry.rows.to_string(), &entry.columns.to_string(), "1"]);
+            assert_eq!(fields[12], entry.metrics.solution_length.unwrap().to_string());
+        }
+        assert_eq!(lines.next(), None);
+
+        let json = serde_json::from_str::<serde_json::Value>(&manifest_json(&entries)).unwrap();
+        let listed = json.as_array().unwrap();
+        assert_eq!(listed.len(), entries.len());
+        for (listed, entry) in listed.iter().zip(&entries) {
+            assert_eq!(listed["file"], entry.file.as_str());
+            assert_eq!(listed["seed"].as_u64(), Some(entry.seed));
+            assert_eq!(listed["fingerprint"].as_u64(), Some(entry.fingerprint));
+            assert_eq!(listed["cells"].as_u64(), Some(entry.metrics.cells as u64));
+        }
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
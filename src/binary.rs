@@ -0,0 +1,447 @@
+use crate::bitset::BitSet;
+use crate::maze::{Maze, Provenance};
+use crate::topology::{self, CellId, Direction, FloorView, Layout, TopologyKind, Wrap};
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Write};
+
+pub const MAGIC: [u8; 4] = *b"MAZE";
+/// Bumped whenever a change would make older readers misread a file.
+pub const FORMAT_VERSION: u8 = 1;
+
+const HAS_SEED: u8 = 1;
+const HAS_GENERATOR: u8 = 1 << 1;
+const HAS_OPENINGS: u8 = 1 << 2;
+const HAS_CROSSINGS: u8 = 1 << 3;
+const HAS_MASK: u8 = 1 << 4;
+const KNOWN_FLAGS: u8 = HAS_SEED | HAS_GENERATOR | HAS_OPENINGS | HAS_CROSSINGS | HAS_MASK;
+
+// A maze record, all numbers little-endian. Records can follow each other in one stream.
+//
+//   magic        4 bytes  "MAZE"
+//   version      u8       FORMAT_VERSION
+//   flags        u8       which optional parts follow, HAS_* above
+//   topology     u8       0 rectangular, 1 hexagonal, 2 triangular, 3 polar
+//   rows         u32
+//   columns      u32
+//   wrap         u8       0 none, 1 cylinder, 2 torus, 3 mobius, 4 klein
+//   floors       u8
+//   floor view   u8       0 single, 1 side by side
+//   mask         rows * columns bits, one per cell, set when the cell is included
+//   seed         u64
+//   generator    u8 length and UTF-8 name, u8 length and UTF-8 version
+//   openings     entrance then exit, each a varint cell and a u8 side
+//   crossings    varint count, then a varint gap from the previous cell and a u8 side each
+//   walls        one bit per slot of `Topology::wall_index`, set when carved
+//
+// Bits are packed eight to a byte, lowest first. Varints are LEB128.
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Writes up to 255 bytes of `text`, cut short between characters so it stays UTF-8.
+fn write_text<W: Write>(writer: &mut W, text: &str) -> io::Result<()> {
+    let length = (0..=text.len().min(u8::MAX as usize)).rev().find(|length| text.is_char_boundary(*length)).unwrap();
+    let bytes = &text.as_bytes()[..length];
+    writer.write_all(&[bytes.len() as u8])?;
+    writer.write_all(bytes)
+}
+
+fn mask_bits(layout: &Layout) -> Option<BitSet> {
+    let rows = layout.mask.as_ref()?;
+    let mut bits = BitSet::new(layout.rows as usize * layout.columns as usize);
+    for (row, text) in rows.iter().enumerate() {
+        for (column, symbol) in text.chars().enumerate() {
+            if symbol == '.' {
+                bits.insert(row * layout.columns as usize + column);
+            }
+        }
+    }
+    Some(bits)
+}
+
+/// Writes one maze record.
+pub fn write<W: Write>(maze: &Maze, writer: &mut W) -> io::Result<()> {
+    let layout = maze.topology().layout();
+    let mask = mask_bits(&layout);
+    let crossings = (0..maze.topology().cell_count()).filter_map(|cell| maze.crossing(cell).map(|under| (cell, under))).collect::<Vec<(CellId, Direction)>>();
+    let provenance = maze.provenance();
+    let openings = maze.entrance().zip(maze.exit());
+
+    let mut flags = 0;
+    for (flag, present) in [
+        (HAS_SEED, provenance.is_some_and(|provenance| provenance.seed.is_some())),
+        (HAS_GENERATOR, provenance.is_some()),
+        (HAS_OPENINGS, openings.is_some()),
+        (HAS_CROSSINGS, !crossings.is_empty()),
+        (HAS_MASK, mask.is_some())
+    ] {
+        if present {
+            flags |= flag;
+        }
+    }
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[FORMAT_VERSION, flags])?;
+    writer.write_all(&[layout.kind as u8])?;
+    writer.write_all(&layout.rows.to_le_bytes())?;
+    writer.write_all(&layout.columns.to_le_bytes())?;
+    writer.write_all(&[layout.wrap as u8, layout.floors, layout.floor_view as u8])?;
+    if let Some(mask) = mask {
+        writer.write_all(&mask.to_bytes())?;
+    }
+    if let Some(provenance) = provenance {
+        if let Some(seed) = provenance.seed {
+            writer.write_all(&seed.to_le_bytes())?;
+        }
+        write_text(writer, &provenance.generator)?;
+        write_text(writer, &provenance.version)?;
+    }
+    if let Some((entrance, exit)) = openings {
+        for (cell, side) in [entrance, exit] {
+            write_varint(writer, cell as u64)?;
+            writer.write_all(&[side as u8])?;
+        }
+    }
+    if !crossings.is_empty() {
+        write_varint(writer, crossings.len() as u64)?;
+        let mut previous = 0;
+        for (cell, under) in crossings {
+            write_varint(writer, (cell - previous) as u64)?;
+            writer.write_all(&[under as u8])?;
+            previous = cell;
+        }
+    }
+    writer.write_all(&maze.passages().to_bytes())
+}
+
+pub fn to_bytes(maze: &Maze) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write(maze, &mut bytes).expect("Writing to memory cannot fail");
+    bytes
+}
+
+fn read_bytes<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; count];
+    reader.read_exact(&mut bytes).map_err(|error| format!("Truncated maze record: {}", error))?;
+    Ok(bytes)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, String> {
+    Ok(read_bytes(reader, 1)?[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(read_bytes(reader, 4)?.try_into().unwrap()))
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(String::from("A varint runs past 64 bits"))
+}
+
+fn read_text<R: Read>(reader: &mut R) -> Result<String, String> {
+    let length = read_u8(reader)? as usize;
+    String::from_utf8(read_bytes(reader, length)?).map_err(|_| String::from("A text field is not UTF-8"))
+}
+
+fn read_cell<R: Read>(reader: &mut R) -> Result<CellId, String> {
+    CellId::try_from(read_varint(reader)?).map_err(|_| String::from("A cell id does not fit this machine"))
+}
+
+/// Reads the rest of a record whose magic has already been read.
+fn read_record<R: Read>(reader: &mut R) -> Result<Maze, String> {
+    let version = read_u8(reader)?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(format!("Format version {} is not one this program reads", version));
+    }
+    let flags = read_u8(reader)?;
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(format!("Unknown flags {:#04x}", flags & !KNOWN_FLAGS));
+    }
+    let kind = match read_u8(reader)? {
+        0 => TopologyKind::Rectangular,
+        1 => TopologyKind::Hexagonal,
+        2 => TopologyKind::Triangular,
+        3 => TopologyKind::Polar,
+        other => return Err(format!("Unknown topology {}", other))
+    };
+    let (rows, columns) = (read_u32(reader)?, read_u32(reader)?);
+    let wrap = match read_u8(reader)? {
+        0 => Wrap::None,
+        1 => Wrap::Cylinder,
+        2 => Wrap::Torus,
+        3 => Wrap::Mobius,
+        4 => Wrap::Klein,
+        other => return Err(format!("Unknown wrap {}", other))
+    };
+    let floors = read_u8(reader)?;
+    let floor_view = match read_u8(reader)? {
+        0 => FloorView::Single,
+        1 => FloorView::SideBySide,
+        other => return Err(format!("Unknown floor view {}", other))
+    };
+    let mut layout = Layout {
+        wrap,
+        floors,
+        floor_view,
+        ..Layout::new(kind, rows, columns)
+    };
+    if flags & HAS_MASK != 0 {
+        let cells = rows as usize * columns as usize;
+        let mask = BitSet::from_bytes(cells, &read_bytes(reader, cells.div_ceil(8))?)?;
+        layout.mask = Some(
+            (0..rows as usize)
+                .map(|row| (0..columns as usize).map(|column| if mask.contains(row * columns as usize + column) { '.' } else { 'X' }).collect())
+                .collect()
+        );
+    }
+    let mut maze = Maze::new(layout.build()?);
+
+    let seed = if flags & HAS_SEED != 0 { Some(u64::from_le_bytes(read_bytes(reader, 8)?.try_into().unwrap())) } else { None };
+    if flags & HAS_GENERATOR != 0 {
+        let generator = read_text(reader)?;
+        let version = read_text(reader)?;
+        maze.set_provenance(Provenance { generator, version, seed });
+    }
+    let topology = maze.topology();
+    let openings = if flags & HAS_OPENINGS != 0 {
+        let mut openings = Vec::new();
+        for name in ["entrance", "exit"] {
+            let opening = (read_cell(reader)?, read_u8(reader)? as Direction);
+            if opening.0 >= topology.cell_count() || !topology.is_included(opening.0) || opening.1 >= topology.direction_count(opening.0) || topology.neighbour(opening.0, opening.1).is_some() {
+                return Err(format!("The {} {:?} is not an outer wall", name, opening));
+            }
+            openings.push(opening);
+        }
+        Some((openings[0], openings[1]))
+    } else {
+        None
+    };
+    let mut crossings = Vec::new();
+    if flags & HAS_CROSSINGS != 0 {
+        let mut cell: CellId = 0;
+        for _ in 0..read_varint(reader)? {
+            cell = cell.checked_add(read_cell(reader)?).filter(|cell| *cell < topology.cell_count()).ok_or_else(|| String::from("A crossing lies past the last cell"))?;
+            let under = read_u8(reader)? as Direction;
+            if !topology::can_cross(topology, cell, under) {
+                return Err(format!("Cell {} cannot have a tunnel under it through side {}", cell, under));
+            }
+            crossings.push((cell, under));
+        }
+    }
+    let wall_count = topology.wall_count();
+    let passages = BitSet::from_bytes(wall_count, &read_bytes(reader, wall_count.div_ceil(8))?)?;
+
+    maze.set_passages(passages)?;
+    for (cell, under) in crossings {
+        maze.set_crossing(cell, under);
+    }
+    if let Some((entrance, exit)) = openings {
+        maze.set_openings(entrance, exit);
+    }
+    Ok(maze)
+}
+
+/// Reads one maze record.
+pub fn read<R: Read>(reader: &mut R) -> Result<Maze, String> {
+    if read_bytes(reader, 4)? != MAGIC {
+        return Err(String::from("Not a maze record: the magic header is missing"));
+    }
+    read_record(reader)
+}
+
+pub fn from_bytes(mut bytes: &[u8]) -> Result<Maze, String> {
+    read(&mut bytes)
+}
+
+/// Reads maze records one after another from a stream, holding only one maze at a time.
+pub struct MazeReader<R: Read> {
+    reader: R,
+    failed: bool
+}
+
+impl<R: Read> MazeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            failed: false
+        }
+    }
+}
+
+impl<R: Read> Iterator for MazeReader<R> {
+    type Item = Result<Maze, String>;
+
+    /// Ends cleanly where a record would start, and after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let mut magic = [0; 4];
+        let mut filled = 0;
+        while filled < magic.len() {
+            match self.reader.read(&mut magic[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(format!("Error reading maze records: {}", error)));
+                }
+            }
+        }
+        let maze = if filled == magic.len() && magic == MAGIC {
+            read_record(&mut self.reader)
+        } else {
+            Err(String::from("Not a maze record: the magic header is missing"))
+        };
+        self.failed = maze.is_err();
+        Some(maze)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::{assert_same_maze, samples};
+
+    #[test]
+    fn round_trips_every_topology() {
+        for (name, maze) in samples() {
+            let loaded = from_bytes(&to_bytes(&maze)).unwrap_or_else(|error| panic!("{}: {}", name, error));
+            assert_same_maze(&loaded, &maze);
+            assert_eq!(loaded.provenance(), maze.provenance(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn reads_records_one_after_another() {
+        let mazes = samples();
+        let bytes = mazes.iter().flat_map(|(_, maze)| to_bytes(maze)).collect::<Vec<u8>>();
+        let loaded = MazeReader::new(&bytes[..]).collect::<Result<Vec<Maze>, String>>().unwrap();
+        assert_eq!(loaded.len(), mazes.len());
+        for ((_, maze), loaded) in mazes.iter().zip(&loaded) {
+            assert_same_maze(loaded, maze);
+        }
+
+        let mut reader = MazeReader::new(&bytes[..bytes.len() - 1]);
+        let results = reader.by_ref().collect::<Vec<Result<Maze, String>>>();
+        assert_eq!(results.len(), mazes.len());
+        assert!(results.last().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    /// The header of a square rectangular record with the given flags.
+    fn header(flags: u8, size: u32) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([FORMAT_VERSION, flags, 0]);
+        bytes.extend(size.to_le_bytes());
+        bytes.extend(size.to_le_bytes());
+        bytes.extend([0, 1, 1]);
+        bytes
+    }
+
+    #[test]
+    fn rejects_corrupt_records() {
+        let bytes = to_bytes(&samples().remove(0).1);
+        assert!(from_bytes(&bytes).is_ok());
+        assert!(from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_bytes(b"MAZ").is_err());
+        assert!(from_bytes(&[b"MAZX".as_slice(), &bytes[4..]].concat()).is_err());
+        assert!(MazeReader::new(&b"NOPE"[..]).next().unwrap().is_err());
+
+        let mut version = bytes.clone();
+        version[4] = FORMAT_VERSION + 1;
+        assert!(from_bytes(&version).is_err());
+        let mut flags = bytes.clone();
+        flags[5] |= 1 << 7;
+        assert!(from_bytes(&flags).is_err());
+        let mut topology = bytes;
+        topology[6] = 9;
+        assert!(from_bytes(&topology).is_err());
+    }
+
+    #[test]
+    fn rejects_varints_that_run_off_the_end() {
+        let mut bytes = header(HAS_OPENINGS, 2);
+        bytes.extend([0x80, 0x80]);
+        assert!(from_bytes(&bytes).err().unwrap().starts_with("Truncated"));
+
+        let mut bytes = header(HAS_OPENINGS, 2);
+        bytes.extend([0xff; 10]);
+        assert!(from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_openings_out_of_range() {
+        // Cell 100 does not exist, side 1 of cell 0 is an inner wall and cell 0 has no side 7.
+        for (cell, side) in [(100, 0), (0, 1), (0, 7)] {
+            let mut bytes = header(HAS_OPENINGS, 2);
+            bytes.extend([cell, side, 0, 3, 0]);
+            assert!(from_bytes(&bytes).err().unwrap().contains("not an outer wall"), "{} {}", cell, side);
+        }
+        let mut bytes = header(HAS_OPENINGS, 2);
+        bytes.extend([0, 0, 0, 3, 0]);
+        assert!(from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn rejects_crossings_with_nowhere_to_go() {
+        // One crossing under cell 0, which sits in the corner of a 2x2 grid.
+        let mut bytes = header(HAS_CROSSINGS, 2);
+        bytes.extend([1, 0, 0, 0]);
+        assert!(from_bytes(&bytes).err().unwrap().contains("cannot have a tunnel"));
+    }
+
+    #[test]
+    fn rejects_crossings_past_the_last_cell() {
+        // A crossing under the middle of a 3x3 grid, then one a gap of 2^64 - 1 further on.
+        let mut bytes = header(HAS_CROSSINGS, 3);
+        bytes.extend([2, 4, 0]);
+        bytes.extend([0xff; 9]);
+        bytes.extend([0x01, 0]);
+        assert!(from_bytes(&bytes).err().unwrap().contains("past the last cell"));
+
+        let mut bytes = header(HAS_CROSSINGS, 3);
+        bytes.extend([1, 9, 0]);
+        assert!(from_bytes(&bytes).err().unwrap().contains("past the last cell"));
+    }
+
+    #[test]
+    fn rejects_openings_in_masked_out_cells() {
+        // Cell 0 of the 2x2 grid is masked out, leaving its top side with nothing inside.
+        let opening = |entrance: [u8; 2]| {
+            let mut bytes = header(HAS_OPENINGS | HAS_MASK, 2);
+            bytes.push(0b1110);
+            bytes.extend(entrance);
+            bytes.extend([3, 1, 0]);
+            from_bytes(&bytes)
+        };
+        assert!(opening([0, 0]).err().unwrap().contains("not an outer wall"));
+        assert!(opening([1, 0]).is_ok());
+    }
+
+    #[test]
+    fn cuts_long_text_between_characters() {
+        let mut maze = samples().remove(0).1;
+        let generator = format!("{}é", "a".repeat(254));
+        maze.set_provenance(Provenance { generator, version: String::from("1"), seed: None });
+        let loaded = from_bytes(&to_bytes(&maze)).unwrap();
+        assert_eq!(loaded.provenance().unwrap().generator, "a".repeat(254));
+    }
+}
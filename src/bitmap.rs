@@ -0,0 +1,487 @@
+use crate::maze::Maze;
+use crate::topology::rectangular::{DOWN, LEFT, RIGHT, UP};
+use crate::topology::{CellId, Direction, Rectangular};
+use image::{DynamicImage, Pixel, RgbaImage};
+
+/// How much darker a band of grid lines has to be than the cells between them, as a share of
+/// the maze's height or width, before it counts as a grid at all.
+const MINIMUM_CONTRAST: f32 = 0.1;
+
+/// Above this share of dark pixels along a wall it is drawn, below `1 - WALL_DRAWN` it is
+/// open, and in between the picture does not say.
+const WALL_DRAWN: f32 = 0.6;
+
+/// More dark pixels than this inside a cell means something other than walls is drawn there.
+const CELL_FILLED: f32 = 0.25;
+
+/// A line of pixels across a cell darker than this is a wall the grid does not account for.
+const CELL_CROSSED: f32 = 0.5;
+
+/// A line of pixels at least this dark, as a share of the maze's height or width, can be part
+/// of the outer wall. Well under a half, so that an entrance and exit on the same side still
+/// leave it standing, but over the faint edges of anti-aliased corners.
+const OUTER_WALL: f32 = 0.25;
+
+/// How many of the best-scoring cell counts along each axis are checked against the picture.
+/// Small mazes give little to go on, and the best score is not always the real grid.
+const CANDIDATES: usize = 4;
+
+/// Grey levels closer than this are the same colour.
+const SAME_LEVEL: i32 = 32;
+
+/// How far the background's grey can stray. The pixels a wall's rounded end only partly
+/// covers must not let the background in.
+const BACKGROUND_NOISE: i32 = 8;
+
+/// A pixel whose red, green and blue differ by more than this is colored in, such as the
+/// entrance, exit and solution markers `render` adds, and is not part of the walls.
+const COLORED: u8 = 48;
+
+/// Where the grid lines run along one axis, as the first pixel and the pixel after the last
+/// of each, outer walls included.
+#[derive(Clone, Debug)]
+struct Axis {
+    lines: Vec<(u32, u32)>
+}
+
+impl Axis {
+    /// Evenly spaced lines `thickness` wide between outer walls of any thickness.
+    fn new(outer: [(u32, u32); 2], thickness: u32, cells: u32) -> Self {
+        let pitch = (outer[1].0 - outer[0].1 + thickness) as f32 / cells as f32;
+        let mut lines = vec![outer[0]];
+        for line in 1..cells {
+            let from = outer[0].1 + (line as f32 * pitch).round() as u32 - thickness;
+            lines.push((from, from + thickness));
+        }
+        lines.push(outer[1]);
+        Self { lines }
+    }
+
+    fn cells(&self) -> u32 {
+        self.lines.len() as u32 - 1
+    }
+
+    /// The pixels strictly between line `index` and the next.
+    fn inside(&self, index: u32) -> (u32, u32) {
+        (self.lines[index as usize].1, self.lines[index as usize + 1].0)
+    }
+
+    fn across(&self, index: u32) -> (u32, u32) {
+        self.lines[index as usize]
+    }
+}
+
+/// Finds the grid along one axis from how many dark pixels each line of pixels across it
+/// holds, starting at pixel `first`. The outer walls are the first and last runs of dark
+/// enough pixels, and may be bolder than the walls inside. Every inner thickness and cell count
+/// that fits is tried. Each splits the pixels into grid lines and everything between, and the
+/// split that explains the most of the variation in darkness scores best, the way Otsu's
+/// method picks a threshold. A grid of every other line is as dark as the real one but covers
+/// half as much, so it scores lower. Returns the best grid for each of the few best cell
+/// counts, best first, or a single cell when no grid stands out.
+fn find_axis(profile: &[u32], first: u32, length: u32, name: &str) -> Result<Vec<Axis>, String> {
+    let density = |position: u32| profile[(position - first) as usize] as f32 / length as f32;
+    let wall = |position: &u32| density(*position) >= OUTER_WALL;
+    let outer = (first..first + profile.len() as u32).filter(wall).collect::<Vec<u32>>();
+    let (start, end) = match outer[..] {
+        [start, .., end] => (start, end + 1),
+        _ => return Err(format!("Could not find the outer wall along the {}", name))
+    };
+    // Lines of pixels just inside the outer wall can be crossed by so many walls that they
+    // look like part of it, so the wall is the part of the run at least half as dark as its
+    // darkest line.
+    let trim = |run: Vec<u32>| {
+        let darkest = run.iter().map(|position| density(*position)).fold(0.0, f32::max);
+        run.into_iter().take_while(|position| density(*position) >= darkest / 2.0).count() as u32
+    };
+    let outer = [
+        (start, start + trim((start..end).take_while(wall).collect())),
+        (end - trim((start..end).rev().take_while(wall).collect()), end)
+    ];
+    if outer[0].1 >= outer[1].0 {
+        return Err(format!("Could not find the outer wall along the {}", name));
+    }
+    let span = outer[1].0 - outer[0].1;
+    let inner = (outer[0].1..outer[1].0).map(density).sum::<f32>();
+
+    let mut candidates = Vec::new();
+    for thickness in 1..=(outer[0].1 - outer[0].0).max(outer[1].1 - outer[1].0) * 2 {
+        for cells in 2..=(span + thickness) / (thickness + 1) {
+            let axis = Axis::new(outer, thickness, cells);
+            let lines = (1..cells).flat_map(|line| { let (from, to) = axis.across(line); from..to }).map(density).sum::<f32>();
+            let line_width = (cells - 1) * thickness;
+            let share = line_width as f32 / span as f32;
+            let contrast = lines / line_width as f32 - (inner - lines) / (span - line_width) as f32;
+            if contrast > MINIMUM_CONTRAST {
+                candidates.push((share * (1.0 - share) * contrast * contrast, axis));
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(vec![Axis::new(outer, 1, 1)]);
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut counts = Vec::new();
+    candidates.retain(|(_, axis)| !counts.contains(&axis.cells()) && { counts.push(axis.cells()); true });
+    candidates.truncate(CANDIDATES);
+    Ok(candidates.into_iter().map(|(_, axis)| axis).collect())
+}
+
+/// The grey level that best splits a histogram into dark and light, by Otsu's method: the one
+/// that leaves the most variance between the two groups.
+fn threshold(histogram: &[u64; 256]) -> Option<u8> {
+    let total = histogram.iter().sum::<u64>() as f64;
+    let sum = histogram.iter().enumerate().map(|(level, count)| level as f64 * *count as f64).sum::<f64>();
+    let (mut dark_count, mut dark_sum) = (0.0, 0.0);
+    let mut best = None;
+    let mut best_variance = 0.0;
+    for (level, count) in histogram.iter().enumerate().take(255) {
+        dark_count += *count as f64;
+        dark_sum += level as f64 * *count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
+        let difference = dark_sum / dark_count - (sum - dark_sum) / light_count;
+        let variance = dark_count * light_count * difference * difference;
+        if variance > best_variance && difference.abs() >= SAME_LEVEL as f64 {
+            best = Some(level as u8 + 1);
+            best_variance = variance;
+        }
+    }
+    best
+}
+
+/// The most common level in part of a histogram.
+fn most_common(histogram: &[u64; 256], levels: std::ops::Range<usize>) -> i32 {
+    levels.max_by_key(|level| histogram[*level]).unwrap() as i32
+}
+
+/// The grey level of a pixel, or `None` for a colored one.
+fn level(pixel: &image::Rgba<u8>) -> Option<i32> {
+    let [red, green, blue, _] = pixel.0;
+    let colored = red.max(green).max(blue) - red.min(green).min(blue) > COLORED;
+    (!colored).then(|| pixel.to_luma()[0] as i32)
+}
+
+/// Sorts the pixels into walls and everything else. The background is the most common grey
+/// around the edge of the image, and the drawing is the box around every pixel of another
+/// grey. In it the walls and the cells are the most common greys either side of the split
+/// that best separates them, and walls are told from the cells by their grey, never from the
+/// background around the maze. Colored pixels are never walls, so markers drawn over the maze
+/// leave what is under them open.
+fn walls(image: &RgbaImage) -> Result<Vec<bool>, String> {
+    let (width, height) = image.dimensions();
+    let no_walls = || String::from("The image has no clear dark walls on a light background");
+    let on_edge = |x: u32, y: u32| x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+    let mut edge = [0u64; 256];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if on_edge(x, y) {
+            if let Some(level) = level(pixel) {
+                edge[level as usize] += 1;
+            }
+        }
+    }
+    let background = most_common(&edge, 0..256);
+
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if level(pixel).is_some_and(|level| (level - background).abs() >= SAME_LEVEL) {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+    }
+    if left >= right {
+        return Err(no_walls());
+    }
+    let mut drawing = [0u64; 256];
+    for (_, _, pixel) in image.enumerate_pixels().filter(|(x, y, _)| (left..right).contains(x) && (top..bottom).contains(y)) {
+        if let Some(level) = level(pixel) {
+            drawing[level as usize] += 1;
+        }
+    }
+    let split = threshold(&drawing).ok_or_else(no_walls)? as usize;
+    let (wall, cell) = (most_common(&drawing, 0..split), most_common(&drawing, split..256));
+
+    // The background reaches in through the gaps in the outer wall. A background as dark as
+    // the walls cannot be told apart from them, and is left to be read as a bolder outer wall.
+    let mut outside = vec![false; (width * height) as usize];
+    if (background - wall).abs() >= SAME_LEVEL {
+        let is_background = |x: u32, y: u32| level(image.get_pixel(x, y)).is_none_or(|level| (level - background).abs() < BACKGROUND_NOISE);
+        let mut stack = image.enumerate_pixels().filter(|(x, y, _)| on_edge(*x, *y)).map(|(x, y, _)| (x, y)).collect::<Vec<(u32, u32)>>();
+        while let Some((x, y)) = stack.pop() {
+            let index = (y * width + x) as usize;
+            if outside[index] || !is_background(x, y) {
+                continue;
+            }
+            outside[index] = true;
+            for (x, y) in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+                if x < width && y < height {
+                    stack.push((x, y));
+                }
+            }
+        }
+    }
+    // Soft edges shade the pixels a wall only partly covers, so a pixel a third of the way
+    // from the cells' grey to the walls' is taken for wall.
+    Ok(image.enumerate_pixels().map(|(x, y, pixel)| {
+        !outside[(y * width + x) as usize] && level(pixel).is_some_and(|level| (cell - level) * 3 >= cell - wall)
+    }).collect())
+}
+
+/// Reads the walls off one grid, checking that every cell is empty and every wall is either
+/// clearly drawn or clearly open. Also counts the dark pixels inside cells, which the grid
+/// leaves unexplained.
+fn read_grid(dark: &impl Fn(u32, u32) -> bool, horizontal: &Axis, vertical: &Axis) -> Result<(Maze, usize), String> {
+    let count = |(x_from, x_to): (u32, u32), (y_from, y_to): (u32, u32)| (y_from..y_to).flat_map(|y| (x_from..x_to).map(move |x| (x, y))).filter(|(x, y)| dark(*x, *y)).count();
+    let share = |x: (u32, u32), y: (u32, u32)| count(x, y) as f32 / ((x.1 - x.0) * (y.1 - y.0)).max(1) as f32;
+    // A thin wall can fall across two half-dark lines of pixels, so a wall is as drawn as the
+    // darkest line of pixels running along it.
+    let drawn = |(x_from, x_to): (u32, u32), (y_from, y_to): (u32, u32), along_x: bool| {
+        if along_x {
+            (y_from..y_to).map(|y| share((x_from, x_to), (y, y + 1))).fold(0.0, f32::max)
+        } else {
+            (x_from..x_to).map(|x| share((x, x + 1), (y_from, y_to))).fold(0.0, f32::max)
+        }
+    };
+    let cell = |row: u32, column: u32| (row * horizontal.cells() + column) as CellId;
+    let mut maze = Maze::new(Box::new(Rectangular::new(vertical.cells(), horizontal.cells())));
+    let mut openings = Vec::<(CellId, Direction)>::new();
+    let mut stray = 0;
+    for row in 0..vertical.cells() {
+        for column in 0..horizontal.cells() {
+            let inside = (horizontal.inside(column), vertical.inside(row));
+            stray += count(inside.0, inside.1);
+            if share(inside.0, inside.1) > CELL_FILLED {
+                return Err(format!("The cell at row {}, column {} is filled in, so the walls do not form a grid", row + 1, column + 1));
+            }
+            if drawn(inside.0, inside.1, true).max(drawn(inside.0, inside.1, false)) > CELL_CROSSED {
+                return Err(format!("A wall runs through the cell at row {}, column {}, so the walls do not form a grid", row + 1, column + 1));
+            }
+            let walls = [
+                (UP, (inside.0, vertical.across(row))),
+                (RIGHT, (horizontal.across(column + 1), inside.1)),
+                (DOWN, (inside.0, vertical.across(row + 1))),
+                (LEFT, (horizontal.across(column), inside.1))
+            ];
+            for (direction, (x, y)) in walls {
+                let outer = maze.topology().neighbour(cell(row, column), direction).is_none();
+                if !outer && (direction == UP || direction == LEFT) {
+                    continue;
+                }
+                let drawn = drawn(x, y, direction == UP || direction == DOWN);
+                if drawn > 1.0 - WALL_DRAWN && drawn < WALL_DRAWN {
+                    return Err(format!("The wall on the {} side of the cell at row {}, column {} is neither clearly drawn nor clearly open", ["top", "right", "bottom", "left"][direction], row + 1, column + 1));
+                }
+                if drawn < WALL_DRAWN {
+                    if outer {
+                        openings.push((cell(row, column), direction));
+                    } else {
+                        maze.carve(cell(row, column), direction);
+                    }
+                }
+            }
+        }
+    }
+    match openings[..] {
+        [] => (),
+        [entrance, exit] => maze.set_openings(entrance, exit),
+        _ => return Err(format!("Expected an entrance and an exit in the outer wall but found {} gaps", openings.len()))
+    }
+    Ok((maze, stray))
+}
+
+/// Reads a maze drawn as dark walls on lighter cells, such as a scan from a puzzle book or a
+/// picture of a rectangular maze from `render::to_image` with dark walls, on any background
+/// lighter than them and with or without its openings and solution marked. Walls thinner
+/// than two pixels only ever half cover a pixel, and need a background the same grey as the
+/// cells. The walls must sit on a regular grid of square or rectangular cells; the cell size
+/// is found from the picture. Gaps in the outer wall become the entrance and exit, in reading order. A maze only a few
+/// cells across with very thick walls can fit more than one grid and may be misread, and a
+/// corridor one cell wide has no walls inside it to count the cells by.
+pub fn import(image: &DynamicImage) -> Result<Maze, String> {
+    let image = image.to_rgba8();
+    let walls = walls(&image)?;
+    let dark = |x: u32, y: u32| walls[(y * image.width() + x) as usize];
+
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, _) in image.enumerate_pixels().filter(|(x, y, _)| dark(*x, *y)) {
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x + 1);
+        bottom = bottom.max(y + 1);
+    }
+    let columns = (left..right).map(|x| (top..bottom).filter(|y| dark(x, *y)).count() as u32).collect::<Vec<u32>>();
+    let rows = (top..bottom).map(|y| (left..right).filter(|x| dark(*x, y)).count() as u32).collect::<Vec<u32>>();
+    let horizontal = find_axis(&columns, left, bottom - top, "width")?;
+    let vertical = find_axis(&rows, top, right - left, "height")?;
+
+    // Several grids can fit a small maze. The one leaving the fewest dark pixels unexplained
+    // wins, and of grids about as good, the one with the fewest cells, so that splitting every
+    // cell in two does not count as a better fit.
+    let mut first_error = None;
+    let mut fits = Vec::new();
+    for horizontal in &horizontal {
+        for vertical in &vertical {
+            match read_grid(&dark, horizontal, vertical) {
+                Ok(fit) => fits.push(fit),
+                Err(error) => first_error = first_error.or(Some(error))
+            }
+        }
+    }
+    let Some(least_stray) = fits.iter().map(|(_, stray)| *stray).min() else {
+        return Err(first_error.expect("Every axis has at least one grid to try"));
+    };
+    let tolerance = columns.iter().sum::<u32>() as usize / 100;
+    let (maze, _) = fits.into_iter().filter(|(_, stray)| *stray <= least_stray + tolerance).min_by_key(|(maze, _)| maze.topology().cell_count()).unwrap();
+    Ok(maze)
+}
+
+pub fn load(path: &str) -> Result<Maze, String> {
+    import(&image::open(path).map_err(|error| format!("Error reading image \"{}\": {}", path, error))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::generated;
+    use crate::render::{self, Style};
+    use image::Rgba;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn read(maze: &Maze, style: &Style) -> Result<Maze, String> {
+        import(&DynamicImage::ImageRgba8(render::to_image(maze, style)))
+    }
+
+    /// The openings come back in reading order, so either may be the entrance.
+    fn assert_read_back(read: &Maze, maze: &Maze, name: &str) {
+        assert_eq!(read.topology().layout(), maze.topology().layout(), "{}", name);
+        assert!(read.passages() == maze.passages(), "{}", name);
+        let openings = |maze: &Maze| {
+            let mut openings = [maze.entrance(), maze.exit()];
+            openings.sort();
+            openings
+        };
+        assert_eq!(openings(read), openings(maze), "{}", name);
+    }
+
+    /// Paints the pixels from `from` up to `to` in `color`.
+    fn paint(image: &mut RgbaImage, from: (u32, u32), to: (u32, u32), color: [u8; 4]) {
+        for y in from.1..to.1 {
+            for x in from.0..to.0 {
+                image.put_pixel(x, y, Rgba(color));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_back_rendered_mazes() {
+        let plain = Style { solution: false, openings: false, heatmap: false, ..Style::default() };
+        let styles = [
+            ("plain", plain.clone()),
+            ("white background", Style { background_color: WHITE, ..plain.clone() }),
+            ("openings", Style { openings: true, ..plain.clone() }),
+            ("openings on white", Style { background_color: WHITE, openings: true, ..plain.clone() }),
+            ("solution", Style { solution: true, openings: true, ..plain.clone() }),
+            ("solution on white", Style { background_color: WHITE, solution: true, ..plain.clone() }),
+            ("dark background", Style { background_color: [0.3, 0.3, 0.3, 1.0], openings: true, ..plain.clone() }),
+            ("thin walls", Style { background_color: WHITE, wall_thickness: 1.0, cell_size: 9.0, ..plain.clone() }),
+            ("thick walls", Style { wall_thickness: 5.0, cell_size: 24.0, margin: 4.0, ..plain })
+        ];
+        // A maze only a cell or two across gives the grid finder little to go on, so these
+        // need more than the single best guess at the cell count along each axis.
+        let sizes = ["2x3", "3x2", "3x3", "6x7", "12x9", "20x20"];
+        for (name, style) in &styles {
+            for size in sizes {
+                for seed in 0..2 {
+                    let maze = generated(&format!("entrance:random\nexit:random\ntable_size:{}", size), seed);
+                    let name = format!("{} {} seed {}", name, size, seed);
+                    let read = read(&maze, style).unwrap_or_else(|error| panic!("{}: {}", name, error));
+                    assert_read_back(&read, &maze, &name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reads_back_mazes_without_openings() {
+        let with_openings = generated("table_size:5x6", 3);
+        let mut maze = Maze::new(Box::new(Rectangular::new(5, 6)));
+        maze.set_passages(with_openings.passages().clone()).unwrap();
+        assert_read_back(&read(&maze, &Style::default()).unwrap(), &maze, "no openings");
+    }
+
+    /// Two gaps in the same side leave less than half of it drawn, which is still enough to
+    /// find it by.
+    #[test]
+    fn reads_both_openings_in_one_side() {
+        for (size, entrance, exit) in [("3x4", "top,0", "top,3"), ("4x3", "left,0", "left,3"), ("5x5", "bottom,1", "bottom,3")] {
+            for seed in 0..4 {
+                let maze = generated(&format!("table_size:{}\nentrance:{}\nexit:{}", size, entrance, exit), seed);
+                assert_read_back(&read(&maze, &Style::default()).unwrap(), &maze, size);
+            }
+        }
+    }
+
+    /// A 4x4 maze drawn black on white with the default sizes, a margin of 10, cells of 20 and
+    /// walls 2 wide, and the pixels on either side of an open wall between two cells in a row.
+    fn open_wall() -> (Maze, RgbaImage, (u32, u32)) {
+        let maze = generated("table_size:4x4", 1);
+        let cell = (0..16).find(|cell| cell % 4 != 3 && maze.is_open(*cell, RIGHT)).unwrap() as u32;
+        let image = render::to_image(&maze, &Style { background_color: WHITE, ..Style::default() });
+        (maze, image, (29 + cell % 4 * 20, 10 + cell / 4 * 20))
+    }
+
+    #[test]
+    fn tells_drawn_walls_from_open_ones() {
+        let (maze, image, (x, y)) = open_wall();
+        assert_read_back(&import(&DynamicImage::ImageRgba8(image.clone())).unwrap(), &maze, "open");
+
+        // Drawn along three quarters of its length, the wall counts as drawn.
+        let mut mostly = image.clone();
+        paint(&mut mostly, (x, y), (x + 2, y + 15), [0, 0, 0, 255]);
+        let read = import(&DynamicImage::ImageRgba8(mostly)).unwrap();
+        assert_eq!(read.passages().count_ones(), maze.passages().count_ones() - 1);
+
+        // Along half of it, the picture does not say.
+        let mut half = image;
+        paint(&mut half, (x, y), (x + 2, y + 10), [0, 0, 0, 255]);
+        let error = import(&DynamicImage::ImageRgba8(half)).err().unwrap();
+        assert!(error.contains("neither clearly drawn nor clearly open"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_lines_through_cells() {
+        let (_, mut image, _) = open_wall();
+        paint(&mut image, (72, 79), (88, 81), [0, 0, 0, 255]);
+        let error = import(&DynamicImage::ImageRgba8(image)).err().unwrap();
+        assert!(error.contains("runs through the cell at row 4, column 4"), "{}", error);
+    }
+
+    #[test]
+    fn ignores_colored_marks() {
+        let (maze, mut image, _) = open_wall();
+        paint(&mut image, (14, 14), (56, 26), [200, 30, 30, 255]);
+        paint(&mut image, (0, 0), (90, 3), [30, 30, 200, 255]);
+        assert_read_back(&import(&DynamicImage::ImageRgba8(image)).unwrap(), &maze, "marked");
+    }
+
+    #[test]
+    fn rejects_pictures_a_pixel_thin() {
+        for (width, height) in [(1, 12), (12, 1), (1, 1)] {
+            let line = RgbaImage::from_fn(width, height, |x, y| Rgba(if (x + y) % 3 == 0 { [0, 0, 0, 255] } else { [255, 255, 255, 255] }));
+            assert!(import(&DynamicImage::ImageRgba8(line)).is_err(), "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn rejects_pictures_without_walls() {
+        let blank = RgbaImage::from_pixel(40, 30, Rgba([255, 255, 255, 255]));
+        assert!(import(&DynamicImage::ImageRgba8(blank)).is_err());
+        let faint = RgbaImage::from_fn(40, 30, |x, _| Rgba(if x % 10 == 0 { [240, 240, 240, 255] } else { [255, 255, 255, 255] }));
+        assert!(import(&DynamicImage::ImageRgba8(faint)).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+/// A fixed number of bits packed 64 to a word. Cheap to clone, compare, hash and save whole.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// The raw words, lowest index in the lowest bit of the first word.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The bits packed eight to a byte, lowest index in the lowest bit of the first byte.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words.iter().flat_map(|word| word.to_le_bytes()).take(self.len.div_ceil(8)).collect()
+    }
+
+    /// Reads bits packed the way `to_bytes` packs them. Bits past `len` must be clear.
+    pub fn from_bytes(len: usize, bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != len.div_ceil(8) {
+            return Err(format!("{} bits take {} bytes but found {}", len, len.div_ceil(8), bytes.len()));
+        }
+        let mut bits = Self::new(len);
+        for (word, chunk) in bits.words.iter_mut().zip(bytes.chunks(8)) {
+            let mut padded = [0; 8];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(padded);
+        }
+        if !len.is_multiple_of(64) && bits.words.last().is_some_and(|word| word >> (len % 64) != 0) {
+            return Err(String::from("Bits are set past the end"));
+        }
+        Ok(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        for len in [0, 1, 7, 8, 9, 63, 64, 65, 130] {
+            let mut bits = BitSet::new(len);
+            for index in (0..len).filter(|index| index % 3 == 0 || index % 7 == 1) {
+                bits.insert(index);
+            }
+            let bytes = bits.to_bytes();
+            assert_eq!(bytes.len(), len.div_ceil(8));
+            assert_eq!(BitSet::from_bytes(len, &bytes).unwrap(), bits);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_lengths_and_stray_bits() {
+        assert!(BitSet::from_bytes(9, &[0]).is_err());
+        assert!(BitSet::from_bytes(9, &[0, 0, 0]).is_err());
+        assert!(BitSet::from_bytes(9, &[0, 0b10]).is_err());
+        assert!(BitSet::from_bytes(9, &[0xff, 0b1]).is_ok());
+    }
+}
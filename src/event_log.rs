@@ -0,0 +1,329 @@
+use crate::generator::Step;
+use crate::maze::Maze;
+use crate::topology::{CellId, Direction, Layout};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::Instant;
+
+/// Something that happened while a maze was generated. A log starts with `Start`, follows with
+/// one event per step of the generator and ends with `Finish`, then `Openings` once the
+/// entrance and exit are cut. `Push` and `Tunnel` carve walls, the other steps only move the
+/// head.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Start { topology: Layout, generator: String, version: String, seed: Option<u64>, start: CellId },
+    Push { from: CellId, direction: Direction, to: CellId },
+    Tunnel { from: CellId, direction: Direction, under: CellId, to: CellId },
+    Pop { to: CellId },
+    Jump { to: CellId },
+    Finish,
+    Openings { entrance: (CellId, Direction), exit: (CellId, Direction) }
+}
+
+impl From<&Step> for Event {
+    fn from(step: &Step) -> Self {
+        match *step {
+            Step::Pushed { from, direction, to } => Event::Push { from, direction, to },
+            Step::Tunnelled { from, direction, under, to } => Event::Tunnel { from, direction, under, to },
+            Step::Popped { to } => Event::Pop { to },
+            Step::Jumped { to } => Event::Jump { to },
+            Step::Finished => Event::Finish
+        }
+    }
+}
+
+/// One line of a log: an event and the microseconds since the log was started.
+///
+/// ```json
+/// {"time_us":0,"event":"start","topology":{"kind":"rectangular",...},"generator":"recursive_backtracker","version":"0.1.5","seed":null,"start":17}
+/// {"time_us":200114,"event":"push","from":17,"direction":1,"to":18}
+/// {"time_us":400231,"event":"pop","to":17}
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub time_us: u64,
+    #[serde(flatten)]
+    pub event: Event
+}
+
+/// Writes the events of one generation as JSON lines, flushing after each so the log holds
+/// everything up to the moment the program stopped.
+pub struct EventLog<W: Write> {
+    writer: W,
+    started: Instant
+}
+
+impl EventLog<BufWriter<File>> {
+    pub fn create(path: &str, maze: &Maze, start: CellId, generator: &str, seed: Option<u64>) -> Result<Self, String> {
+        let file = File::create(path).map_err(|error| format!("Error creating the event log {}: {}", path, error))?;
+        Self::new(BufWriter::new(file), maze, start, generator, seed)
+    }
+}
+
+impl<W: Write> EventLog<W> {
+    /// Starts a log for generating `maze` from `start`, writing the `Start` event.
+    pub fn new(writer: W, maze: &Maze, start: CellId, generator: &str, seed: Option<u64>) -> Result<Self, String> {
+        let mut log = Self {
+            writer,
+            started: Instant::now()
+        };
+        log.record(Event::Start {
+            topology: maze.topology().layout(),
+            generator: String::from(generator),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            seed,
+            start
+        })?;
+        Ok(log)
+    }
+
+    pub fn record(&mut self, event: Event) -> Result<(), String> {
+        let record = Record {
+            time_us: self.started.elapsed().as_micros() as u64,
+            event
+        };
+        serde_json::to_writer(&mut self.writer, &record).map_err(|error| format!("Error writing the event log: {}", error))?;
+        writeln!(self.writer).and_then(|_| self.writer.flush()).map_err(|error| format!("Error writing the event log: {}", error))
+    }
+
+    pub fn record_step(&mut self, step: &Step) -> Result<(), String> {
+        self.record(Event::from(step))
+    }
+}
+
+/// Reads every record of a log.
+pub fn read<R: BufRead>(reader: R) -> Result<Vec<Record>, String> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(index, line)| {
+            let line = line.map_err(|error| format!("Error reading the event log: {}", error))?;
+            serde_json::from_str::<Record>(&line).map_err(|error| format!("Invalid event on line {}: {}", index + 1, error))
+        })
+        .collect()
+}
+
+/// Plays a recorded generation back one event per `step`, the way `Backtracker` would have
+/// generated it, without running the algorithm. Every event is checked against the logged
+/// topology up front, so playing back cannot fail halfway. A log that stops before `Finish`
+/// plays back as far as it goes.
+pub struct Replay {
+    start: CellId,
+    steps: Vec<Event>,
+    next: usize,
+    head: Option<CellId>,
+    openings: Option<((CellId, Direction), (CellId, Direction))>
+}
+
+impl Replay {
+    /// Reads a log file, returning the empty maze to play it back on and the replay.
+    pub fn load(path: &str) -> Result<(Maze, Self), String> {
+        let file = File::open(path).map_err(|error| format!("Error opening the event log {}: {}", path, error))?;
+        Self::new(read(BufReader::new(file))?)
+    }
+
+    pub fn new(records: Vec<Record>) -> Result<(Maze, Self), String> {
+        let mut records = records.into_iter().map(|record| record.event);
+        let (layout, start) = match records.next() {
+            Some(Event::Start { topology, start, .. }) => (topology, start),
+            _ => return Err(String::from("An event log must begin with a start event"))
+        };
+        let maze = Maze::new(layout.build()?);
+        if start >= maze.topology().cell_count() || !maze.topology().is_included(start) {
+            return Err(format!("The start cell {} is not in the maze", start));
+        }
+        let mut replay = Self {
+            start,
+            steps: Vec::new(),
+            next: 0,
+            head: Some(start),
+            openings: None
+        };
+        let mut check = Maze::new(layout.build()?);
+        for (index, event) in records.enumerate() {
+            let line = index + 2;
+            if replay.openings.is_some() {
+                return Err(format!("Event on line {} comes after the openings", line));
+            }
+            if replay.steps.last() == Some(&Event::Finish) && !matches!(event, Event::Openings { .. }) {
+                return Err(format!("Event on line {} comes after the finish", line));
+            }
+            match event {
+                Event::Start { .. } => return Err(format!("A second start event on line {}", line)),
+                Event::Openings { entrance, exit } => {
+                    for opening in [entrance, exit] {
+                        if !is_outer_wall(&check, opening) {
+                            return Err(format!("The opening {:?} on line {} is not an outer wall", opening, line));
+                        }
+                    }
+                    replay.openings = Some((entrance, exit));
+                },
+                event => {
+                    apply(&mut check, &event).map_err(|error| format!("{} on line {}", error, line))?;
+                    replay.steps.push(event);
+                }
+            }
+        }
+        Ok((maze, replay))
+    }
+
+    pub fn start(&self) -> CellId {
+        self.start
+    }
+
+    pub fn head(&self) -> Option<CellId> {
+        self.head
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.steps.len()
+    }
+
+    /// The entrance and exit the log ends with, if it got that far.
+    pub fn openings(&self) -> Option<((CellId, Direction), (CellId, Direction))> {
+        self.openings
+    }
+
+    /// Applies the next event to `maze` and returns it as the step the generator took.
+    pub fn step(&mut self, maze: &mut Maze) -> Step {
+        let step = match self.steps.get(self.next) {
+            Some(event) => apply(maze, event).expect("Replayed events are checked when the log is read"),
+            None => Step::Finished
+        };
+        self.next = (self.next + 1).min(self.steps.len());
+        self.head = match step {
+            Step::Pushed { to, .. } | Step::Tunnelled { to, .. } | Step::Popped { to } | Step::Jumped { to } => Some(to),
+            Step::Finished => None
+        };
+        step
+    }
+}
+
+fn is_outer_wall(maze: &Maze, (cell, direction): (CellId, Direction)) -> bool {
+    let topology = maze.topology();
+    is_cell(maze, cell) && direction < topology.direction_count(cell) && topology.neighbour(cell, direction).is_none()
+}
+
+fn is_cell(maze: &Maze, cell: CellId) -> bool {
+    cell < maze.topology().cell_count() && maze.topology().is_included(cell)
+}
+
+/// Carves what a step event carved, after checking that the topology allows it.
+fn apply(maze: &mut Maze, event: &Event) -> Result<Step, String> {
+    let topology = maze.topology();
+    match *event {
+        Event::Push { from, direction, to } => {
+            if !is_cell(maze, from) || direction >= topology.direction_count(from) || topology.neighbour(from, direction) != Some(to) {
+                return Err(format!("Cannot push from {} through side {} to {}", from, direction, to));
+            }
+            maze.carve(from, direction);
+            Ok(Step::Pushed { from, direction, to })
+        },
+        Event::Tunnel { from, direction, under, to } => {
+            let exit = (is_cell(maze, from) && direction < topology.direction_count(from) && topology.neighbour(from, direction) == Some(under))
+                .then(|| topology.straight_on(under, topology.opposite(from, direction)))
+                .flatten();
+            if exit.and_then(|exit| topology.neighbour(under, exit)) != Some(to) || maze.crossing(under).is_some() {
+                return Err(format!("Cannot tunnel from {} through side {} under {} to {}", from, direction, under, to));
+            }
+            maze.tunnel(from, direction);
+            Ok(Step::Tunnelled { from, direction, under, to })
+        },
+        Event::Pop { to } | Event::Jump { to } if !is_cell(maze, to) => Err(format!("Cell {} is not in the maze", to)),
+        Event::Pop { to } => Ok(Step::Popped { to }),
+        Event::Jump { to } => Ok(Step::Jumped { to }),
+        Event::Finish => Ok(Step::Finished),
+        Event::Start { .. } | Event::Openings { .. } => Err(String::from("Not a step of the generator"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::Generation;
+    use crate::generator;
+    use crate::maze::tests::assert_same_maze;
+
+    /// Logs a whole generation to memory, returning the finished maze and the log's text.
+    fn logged(settings: &str, seed: u64) -> (Maze, String) {
+        let settings = settings.parse().unwrap();
+        let mut generation = Generation::new(&settings, seed).unwrap();
+        let start = generation.backtracker().start();
+        let mut log = EventLog::new(Vec::new(), generation.maze(), start, generator::name(settings.weave), Some(seed)).unwrap();
+        while !generation.is_finished() {
+            log.record_step(&generation.step()).unwrap();
+        }
+        let maze = generation.finish();
+        log.record(Event::Openings { entrance: maze.entrance().unwrap(), exit: maze.exit().unwrap() }).unwrap();
+        (maze, String::from_utf8(log.writer).unwrap())
+    }
+
+    fn replayed(text: &str) -> Result<(Maze, Replay), String> {
+        Replay::new(read(text.as_bytes())?)
+    }
+
+    #[test]
+    fn replays_what_it_logged() {
+        for settings in ["table_size:5x6", "table_size:10x10\nweave:true", "table_size:4x4\ntopology:hexagonal"] {
+            let (maze, text) = logged(settings, 7);
+            let records = read(text.as_bytes()).unwrap();
+            assert!(matches!(&records[0].event, Event::Start { seed: Some(7), .. }), "{}", settings);
+            assert!(records.windows(2).all(|pair| pair[0].time_us <= pair[1].time_us), "{}", settings);
+
+            let (mut replayed, mut replay) = Replay::new(records).unwrap();
+            while !replay.is_finished() {
+                replay.step(&mut replayed);
+            }
+            let (entrance, exit) = replay.openings().unwrap();
+            replayed.set_openings(entrance, exit);
+            assert_same_maze(&replayed, &maze);
+        }
+    }
+
+    #[test]
+    fn rejects_logs_without_a_start() {
+        let (_, text) = logged("table_size:3x3", 0);
+        let without_start = text.lines().skip(1).collect::<Vec<&str>>().join("\n");
+        assert!(replayed(&without_start).err().unwrap().contains("must begin with a start event"));
+        assert!(replayed("").is_err());
+        assert!(replayed("{\"time_us\":0,\"event\":\"begin\"}").err().unwrap().contains("line 1"));
+    }
+
+    #[test]
+    fn rejects_cells_outside_the_maze() {
+        let (_, text) = logged("table_size:3x3", 0);
+        let mut records = read(text.as_bytes()).unwrap();
+        if let Event::Start { start, .. } = &mut records[0].event {
+            *start = 9;
+        }
+        assert!(Replay::new(records).err().unwrap().contains("start cell 9"));
+
+        let start = text.lines().next().unwrap();
+        for step in ["{\"time_us\":1,\"event\":\"jump\",\"to\":9}", "{\"time_us\":1,\"event\":\"push\",\"from\":8,\"direction\":1,\"to\":9}"] {
+            assert!(replayed(&format!("{}\n{}", start, step)).err().unwrap().ends_with("on line 2"), "{}", step);
+        }
+        let opening = "{\"time_us\":1,\"event\":\"openings\",\"entrance\":[4,0],\"exit\":[0,0]}";
+        assert!(replayed(&format!("{}\n{}", start, opening)).err().unwrap().contains("not an outer wall"));
+    }
+
+    #[test]
+    fn rejects_events_out_of_order() {
+        let (_, text) = logged("table_size:3x3", 0);
+        let lines = text.lines().collect::<Vec<&str>>();
+        let finish = lines.iter().position(|line| line.contains("\"finish\"")).unwrap();
+
+        let after_finish = [&lines[..=finish], &lines[1..2], &lines[finish + 1..]].concat().join("\n");
+        assert!(replayed(&after_finish).err().unwrap().contains("after the finish"));
+        let after_openings = [&lines[..], &lines[1..2]].concat().join("\n");
+        assert!(replayed(&after_openings).err().unwrap().contains("after the openings"));
+        let second_start = [&lines[..2], &lines[..1], &lines[2..]].concat().join("\n");
+        assert!(replayed(&second_start).err().unwrap().contains("second start"));
+        // A log that stops before the finish still plays back as far as it goes.
+        let cut_short = lines[..finish].join("\n");
+        let (_, replay) = replayed(&cut_short).unwrap();
+        assert!(replay.openings().is_none());
+    }
+}
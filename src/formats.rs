@@ -0,0 +1,141 @@
+use crate::maze::Maze;
+use crate::render::{self, Style};
+use crate::{ascii, binary, bitmap, json, solver, terminal};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The ways a maze can be written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Binary,
+    Ascii,
+    Terminal,
+    Svg,
+    Png,
+    Gif
+}
+
+pub const FORMATS: [Format; 7] = [Format::Json, Format::Binary, Format::Ascii, Format::Terminal, Format::Svg, Format::Png, Format::Gif];
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        FORMATS
+            .iter()
+            .copied()
+            .find(|format| format.name() == text.trim().to_lowercase())
+            .ok_or_else(|| format!("Unknown format \"{}\", expected one of {}", text.trim(), FORMATS.map(|format| format.name()).join(", ")))
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "binary",
+            Format::Ascii => "ascii",
+            Format::Terminal => "terminal",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Gif => "gif"
+        }
+    }
+
+    /// The file extension the format is written with, which `from_path` recognises.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "maze",
+            Format::Ascii => "txt",
+            Format::Terminal => "ans",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Gif => "gif"
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        FORMATS.iter().copied().find(|format| format.extension() == extension)
+    }
+
+    /// Whether the format can draw a solution on top of the maze.
+    pub fn shows_solution(&self) -> bool {
+        matches!(self, Format::Terminal | Format::Svg | Format::Png | Format::Gif)
+    }
+}
+
+/// Writes a finished maze in `format`, with the solution and other extras drawn the way
+/// `style` asks. GIFs animate the generation itself, so they are written while generating by
+/// `render::save_generation_gif` instead.
+pub fn to_bytes(maze: &Maze, format: Format, style: &Style) -> Result<Vec<u8>, String> {
+    Ok(match format {
+        Format::Json => (json::to_json(maze) + "\n").into_bytes(),
+        Format::Binary => binary::to_bytes(maze),
+        Format::Ascii => ascii::export(maze)?.into_bytes(),
+        Format::Terminal => {
+            let solution = solver::solve(maze).filter(|_| style.solution);
+            terminal::render(maze, solution.as_deref())?.into_bytes()
+        },
+        Format::Svg => render::to_svg(maze, style).into_bytes(),
+        Format::Png => {
+            let image = render::to_image(maze, style);
+            let mut bytes = Vec::new();
+            image::codecs::png::PngEncoder::new(&mut bytes)
+                .encode(&image, image.width(), image.height(), image::ColorType::Rgba8)
+                .map_err(|error| format!("Error encoding the PNG image: {}", error))?;
+            bytes
+        },
+        Format::Gif => return Err(String::from("A GIF animates generating the maze, so only a newly generated maze can be written as one"))
+    })
+}
+
+/// Reads a maze in any format it can be read back from, telling them apart by their contents:
+/// binary records by their magic header, PNG and BMP images by theirs, JSON by its opening
+/// brace and anything else as ASCII art.
+pub fn from_bytes(bytes: &[u8]) -> Result<Maze, String> {
+    if bytes.starts_with(&binary::MAGIC) {
+        return binary::from_bytes(bytes);
+    }
+    if bytes.starts_with(b"\x89PNG") || bytes.starts_with(b"BM") {
+        let image = image::load_from_memory(bytes).map_err(|error| format!("Error reading the image: {}", error))?;
+        return bitmap::import(&image);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| String::from("Not a maze: expected a binary record, an image, JSON or ASCII art"))?;
+    if text.trim_start().starts_with('{') {
+        json::from_json(text)
+    } else {
+        ascii::import(text)
+    }
+}
+
+/// Reads a maze from a file, or from standard input for `-`.
+pub fn load(path: &str) -> Result<Maze, String> {
+    let mut bytes = Vec::new();
+    if path == "-" {
+        io::stdin().read_to_end(&mut bytes).map_err(|error| format!("Error reading standard input: {}", error))?;
+    } else {
+        bytes = fs::read(path).map_err(|error| format!("Error reading {}: {}", path, error))?;
+    }
+    from_bytes(&bytes).map_err(|error| if path == "-" { error } else { format!("{}: {}", path, error) })
+}
+
+/// Writes bytes to a file, or to standard output for `-`.
+pub fn save(bytes: &[u8], path: &str) -> Result<(), String> {
+    if path == "-" {
+        let mut stdout = io::stdout();
+        return stdout.write_all(bytes).and_then(|_| stdout.flush()).map_err(|error| format!("Error writing to standard output: {}", error));
+    }
+    fs::write(path, bytes).map_err(|error| format!("Error writing {}: {}", path, error))
+}
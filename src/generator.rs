@@ -0,0 +1,262 @@
+use crate::maze::Maze;
+use crate::topology::{CellId, Direction, Topology};
+use rand::Rng;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub enum Step {
+    Pushed { from: CellId, direction: Direction, to: CellId },
+    Popped { to: CellId },
+    Jumped { to: CellId },
+    Tunnelled { from: CellId, direction: Direction, under: CellId, to: CellId },
+    Finished
+}
+
+/// The name saved mazes record for this generator, with or without weaving.
+pub fn name(weave: bool) -> &'static str {
+    if weave { "recursive_backtracker_weave" } else { "recursive_backtracker" }
+}
+
+/// A cell to start generating from, picked evenly among the cells the topology includes.
+pub fn random_start<R: Rng>(topology: &dyn Topology, rng: &mut R) -> CellId {
+    let included_cells = (0..topology.cell_count()).filter(|cell| topology.is_included(*cell)).collect::<Vec<CellId>>();
+    included_cells[rng.gen_range(0..included_cells.len())]
+}
+
+/// What generating a whole maze in one go cost.
+#[derive(Clone, Copy, Debug)]
+pub struct Report {
+    pub steps: u64,
+    pub elapsed: Duration,
+    pub peak_depth: usize
+}
+
+impl Report {
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "Generated in {:.3} ms: {} steps ({:.0} steps/s), peak stack depth {}",
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.steps,
+            self.steps_per_second(),
+            self.peak_depth
+        )
+    }
+}
+
+/// Recursive backtracker, one move per `step`. It only asks the topology for neighbours, so it
+/// works on any grid shape. When a mask splits the layout into separate regions, it jumps to the
+/// next unvisited region once the current one is done. With `weave` it may also tunnel under a
+/// visited cell whose passage runs straight across its path.
+pub struct Backtracker {
+    start: CellId,
+    taken_paths: Vec<CellId>,
+    visited: Vec<bool>,
+    next_unvisited: CellId,
+    weave: bool,
+    finished: bool
+}
+
+impl Backtracker {
+    pub fn new(maze: &Maze, start: CellId, weave: bool) -> Self {
+        let topology = maze.topology();
+        let mut visited = (0..topology.cell_count()).map(|cell| !topology.is_included(cell)).collect::<Vec<bool>>();
+        visited[start] = true;
+        Self {
+            start,
+            taken_paths: vec![start],
+            visited,
+            next_unvisited: 0,
+            weave,
+            finished: false
+        }
+    }
+
+    pub fn start(&self) -> CellId {
+        self.start
+    }
+
+    pub fn head(&self) -> Option<CellId> {
+        self.taken_paths.last().copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the generator has reached `cell` yet. Cells left out of the topology count as
+    /// reached from the start.
+    pub fn is_visited(&self, cell: CellId) -> bool {
+        self.visited[cell]
+    }
+
+    /// How many cells are on the stack, the head included.
+    pub fn depth(&self) -> usize {
+        self.taken_paths.len()
+    }
+
+    /// Steps until the maze is finished, as fast as possible.
+    pub fn run<R: Rng>(&mut self, maze: &mut Maze, rng: &mut R) -> Report {
+        let started = Instant::now();
+        let mut steps = 0;
+        let mut peak_depth = self.depth();
+        while !self.finished {
+            self.step(maze, rng);
+            steps += 1;
+            peak_depth = peak_depth.max(self.depth());
+        }
+        Report {
+            steps,
+            elapsed: started.elapsed(),
+            peak_depth
+        }
+    }
+
+    pub fn step<R: Rng>(&mut self, maze: &mut Maze, rng: &mut R) -> Step {
+        let head = match self.head() {
+            Some(head) => head,
+            None => return self.jump_to_unvisited()
+        };
+        let topology = maze.topology();
+        let mut choices = (0..topology.direction_count(head))
+            .filter_map(|direction| topology.neighbour(head, direction).map(|next| (direction, next)))
+            .filter(|(_, next)| !self.visited[*next])
+            .map(|(direction, next)| (direction, next, false))
+            .collect::<Vec<(Direction, CellId, bool)>>();
+        if self.weave {
+            choices.extend(
+                (0..topology.direction_count(head))
+                    .filter_map(|direction| self.tunnel_exit(maze, head, direction).map(|next| (direction, next, true)))
+            );
+        }
+        if choices.is_empty() {
+            self.taken_paths.pop();
+            return match self.head() {
+                Some(to) => Step::Popped { to },
+                None => self.jump_to_unvisited()
+            };
+        }
+        let (direction, next, tunnelled) = choices[rng.gen_range(0..choices.len())];
+        self.visited[next] = true;
+        self.taken_paths.push(next);
+        if tunnelled {
+            let under = maze.topology().neighbour(head, direction).unwrap();
+            maze.tunnel(head, direction);
+            return Step::Tunnelled { from: head, direction, under, to: next };
+        }
+        maze.carve(head, direction);
+        Step::Pushed { from: head, direction, to: next }
+    }
+
+    /// Where a tunnel from `head` in `direction` would come out, if the neighbour there is a
+    /// visited straight passage running across the way and the cell beyond is still unvisited.
+    /// Topologies without `straight_on` never tunnel.
+    fn tunnel_exit(&self, maze: &Maze, head: CellId, direction: Direction) -> Option<CellId> {
+        let topology = maze.topology();
+        let under = topology.neighbour(head, direction)?;
+        if !self.visited[under] || maze.crossing(under).is_some() {
+            return None;
+        }
+        let entry = topology.opposite(head, direction);
+        let exit = topology.straight_on(under, entry)?;
+        let open = (0..topology.direction_count(under)).filter(|side| maze.is_open(under, *side)).collect::<Vec<Direction>>();
+        let crosses = match open[..] {
+            [side, across] => side != entry && side != exit && across != entry && across != exit && topology.straight_on(under, side) == Some(across),
+            _ => false
+        };
+        let beyond = topology.neighbour(under, exit)?;
+        if crosses && !self.visited[beyond] && beyond != head {
+            Some(beyond)
+        } else {
+            None
+        }
+    }
+
+    fn jump_to_unvisited(&mut self) -> Step {
+        while self.next_unvisited < self.visited.len() && self.visited[self.next_unvisited] {
+            self.next_unvisited += 1;
+        }
+        if self.next_unvisited == self.visited.len() {
+            self.finished = true;
+            return Step::Finished;
+        }
+        let to = self.next_unvisited;
+        self.visited[to] = true;
+        self.taken_paths.push(to);
+        Step::Jumped { to }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::generated;
+    use crate::topology::{self, Layout, TopologyKind};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn reports_steps_and_depth() {
+        for (rows, columns) in [(1, 1), (1, 9), (6, 7), (12, 12)] {
+            let cells = rows as u64 * columns as u64;
+            let run = || {
+                let maze = Maze::new(Layout::new(TopologyKind::Rectangular, rows, columns).build().unwrap());
+                let backtracker = Backtracker::new(&maze, 0, false);
+                (maze, backtracker)
+            };
+
+            let (mut maze, mut backtracker) = run();
+            let report = backtracker.run(&mut maze, &mut StdRng::seed_from_u64(11));
+            // Every cell but the start is pushed once and every cell popped once, the last pop
+            // finishing the maze.
+            assert_eq!(report.steps, cells * 2 - 1, "{}x{}", rows, columns);
+            assert!(report.peak_depth >= 1 && report.peak_depth as u64 <= cells, "{}x{}", rows, columns);
+
+            let (mut maze, mut backtracker) = run();
+            let mut rng = StdRng::seed_from_u64(11);
+            let mut deepest = backtracker.depth();
+            while !backtracker.is_finished() {
+                backtracker.step(&mut maze, &mut rng);
+                deepest = deepest.max(backtracker.depth());
+            }
+            assert_eq!(report.peak_depth, deepest, "{}x{}", rows, columns);
+        }
+        let straight = {
+            let mut maze = Maze::new(Layout::new(TopologyKind::Rectangular, 1, 9).build().unwrap());
+            Backtracker::new(&maze, 0, false).run(&mut maze, &mut StdRng::seed_from_u64(11))
+        };
+        assert_eq!(straight.peak_depth, 9);
+    }
+
+    #[test]
+    fn describes_a_report() {
+        let report = Report { steps: 1500, elapsed: Duration::from_millis(3), peak_depth: 42 };
+        assert_eq!(report.to_string(), "Generated in 3.000 ms: 1500 steps (500000 steps/s), peak stack depth 42");
+    }
+
+    #[test]
+    fn tunnels_only_under_straight_passages() {
+        let mut crossings = 0;
+        for seed in 0..8 {
+            let maze = generated("weave:true\ntable_size:10x10", seed);
+            let topology = maze.topology();
+            for cell in 0..topology.cell_count() {
+                if let Some(under) = maze.crossing(cell) {
+                    crossings += 1;
+                    assert!(topology::can_cross(topology, cell, under), "seed {} cell {}", seed, cell);
+                    let across = topology.straight_on(cell, under).unwrap();
+                    assert!((0..topology.direction_count(cell)).all(|side| maze.is_open(cell, side)), "seed {} cell {}", seed, cell);
+                    assert_eq!(maze.links(cell).len(), 2, "seed {} cell {}", seed, cell);
+                    assert!(maze.link(cell, under).is_none() && maze.link(cell, across).is_none(), "seed {} cell {}", seed, cell);
+                }
+            }
+        }
+        assert!(crossings > 0);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::maze::{Maze, Provenance};
+use crate::topology::{self, CellId, Direction, Layout};
+use serde::{Deserialize, Serialize};
+
+/// Written into every file so readers can tell it apart from other JSON.
+pub const FORMAT: &str = "maze_maker";
+/// Bumped whenever a change would make older readers misread a file.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The generator that made a maze and the crate version it shipped in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Generator {
+    pub name: String,
+    pub version: String
+}
+
+/// A maze as JSON. Cells are numbered the way the topology numbers them (row by row for grids,
+/// ring by ring from the center for polar ones, floor by floor for several floors) and sides
+/// the way it numbers directions, for example up, right, down, left on rectangular grids.
+///
+/// ```json
+/// {
+///   "format": "maze_maker",
+///   "version": 1,
+///   "generator": { "name": "recursive_backtracker", "version": "0.1.5" },
+///   "seed": 42,
+///   "topology": { "kind": "rectangular", "rows": 2, "columns": 2, "wrap": "none", "mask": null, "floors": 1, "floor_view": "side_by_side" },
+///   "cells": 4,
+///   "passages": [10, 12, 10, 9],
+///   "crossings": [],
+///   "entrance": [0, 3],
+///   "exit": [2, 3]
+/// }
+/// ```
+///
+/// `passages` holds one number per cell with bit `d` set when side `d` is open, the entrance
+/// and exit included, so both cells of a carved wall have their bit set. `crossings` lists
+/// `[cell, side]` for every cell a tunnel runs under, with the lower of the two sides the
+/// tunnel passes through. `entrance` and `exit` are `[cell, side]` of outer walls, or null
+/// while the maze has no openings. `generator` and `seed` are null when unknown.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeFile {
+    pub format: String,
+    pub version: u32,
+    pub generator: Option<Generator>,
+    pub seed: Option<u64>,
+    pub topology: Layout,
+    pub cells: usize,
+    pub passages: Vec<u8>,
+    #[serde(default)]
+    pub crossings: Vec<(CellId, Direction)>,
+    pub entrance: Option<(CellId, Direction)>,
+    pub exit: Option<(CellId, Direction)>
+}
+
+impl MazeFile {
+    pub fn from_maze(maze: &Maze) -> Self {
+        let topology = maze.topology();
+        let passages = (0..topology.cell_count())
+            .map(|cell| {
+                (0..topology.direction_count(cell))
+                    .filter(|direction| maze.is_open(cell, *direction))
+                    .fold(0, |bits, direction| bits | 1 << direction)
+            })
+            .collect();
+        let crossings = (0..topology.cell_count()).filter_map(|cell| maze.crossing(cell).map(|under| (cell, under))).collect();
+        Self {
+            format: String::from(FORMAT),
+            version: FORMAT_VERSION,
+            generator: maze.provenance().map(|provenance| Generator { name: provenance.generator.clone(), version: provenance.version.clone() }),
+            seed: maze.provenance().and_then(|provenance| provenance.seed),
+            topology: topology.layout(),
+            cells: topology.cell_count(),
+            passages,
+            crossings,
+            entrance: maze.entrance(),
+            exit: maze.exit()
+        }
+    }
+
+    /// Rebuilds the maze, checking that the file describes one consistently.
+    pub fn to_maze(&self) -> Result<Maze, String> {
+        if self.format != FORMAT {
+            return Err(format!("Expected a {} file but found format \"{}\"", FORMAT, self.format));
+        }
+        if self.version > FORMAT_VERSION {
+            return Err(format!("Format version {} is newer than the {} this program reads", self.version, FORMAT_VERSION));
+        }
+        let mut maze = Maze::new(self.topology.build()?);
+        let topology = maze.topology();
+        if self.cells != topology.cell_count() || self.passages.len() != topology.cell_count() {
+            return Err(format!("The topology has {} cells but the file lists {} with {} passages", topology.cell_count(), self.cells, self.passages.len()));
+        }
+        let outer_wall = |(cell, direction): (CellId, Direction)| {
+            cell < topology.cell_count() && topology.is_included(cell) && direction < topology.direction_count(cell) && topology.neighbour(cell, direction).is_none()
+        };
+        for (name, opening) in [("entrance", self.entrance), ("exit", self.exit)] {
+            if opening.is_some_and(|opening| !outer_wall(opening)) {
+                return Err(format!("The {} {:?} is not an outer wall", name, opening.unwrap()));
+            }
+        }
+        for (cell, under) in self.crossings.iter().copied() {
+            if !topology::can_cross(topology, cell, under) {
+                return Err(format!("Cell {} cannot have a tunnel under it through side {}", cell, under));
+            }
+        }
+        for cell in 0..self.cells {
+            for direction in (0..maze.topology().direction_count(cell)).filter(|direction| self.passages[cell] & 1 << direction != 0) {
+                maze.carve(cell, direction);
+            }
+        }
+        for (cell, under) in self.crossings.iter().copied() {
+            maze.set_crossing(cell, under);
+        }
+        match (self.entrance, self.exit) {
+            (Some(entrance), Some(exit)) => maze.set_openings(entrance, exit),
+            (None, None) => (),
+            _ => return Err(String::from("A maze needs both an entrance and an exit, or neither"))
+        }
+        if let (Some(generator), seed) = (&self.generator, self.seed) {
+            maze.set_provenance(Provenance { generator: generator.name.clone(), version: generator.version.clone(), seed });
+        }
+        if MazeFile::from_maze(&maze).passages != self.passages {
+            return Err(String::from("The passages do not match up: a wall is open from one side only, or an outer wall is open without being the entrance or exit"));
+        }
+        Ok(maze)
+    }
+}
+
+pub fn to_json(maze: &Maze) -> String {
+    serde_json::to_string_pretty(&MazeFile::from_maze(maze)).expect("Mazes always serialize")
+}
+
+pub fn from_json(text: &str) -> Result<Maze, String> {
+    serde_json::from_str::<MazeFile>(text).map_err(|error| format!("Invalid maze JSON: {}", error))?.to_maze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::{assert_same_maze, samples};
+
+    #[test]
+    fn round_trips_every_topology() {
+        for (name, maze) in samples() {
+            let loaded = from_json(&to_json(&maze)).unwrap_or_else(|error| panic!("{}: {}", name, error));
+            assert_same_maze(&loaded, &maze);
+            assert_eq!(loaded.provenance(), maze.provenance(), "{}", name);
+        }
+    }
+
+    fn edited(edit: impl FnOnce(&mut MazeFile)) -> Result<Maze, String> {
+        let (_, maze) = samples().remove(0);
+        let mut file = MazeFile::from_maze(&maze);
+        edit(&mut file);
+        from_json(&serde_json::to_string(&file).unwrap())
+    }
+
+    #[test]
+    fn rejects_inconsistent_files() {
+        assert!(edited(|_| ()).is_ok());
+        assert!(edited(|file| file.format = String::from("other")).is_err());
+        assert!(edited(|file| file.version = FORMAT_VERSION + 1).is_err());
+        assert!(edited(|file| file.entrance = Some((1000, 0))).is_err());
+        assert!(edited(|file| file.exit = Some((0, 9))).is_err());
+        assert!(edited(|file| file.entrance = Some((8, 0))).is_err());
+        assert!(edited(|file| file.exit = None).is_err());
+        assert!(edited(|file| file.crossings.push((0, 0))).is_err());
+        assert!(edited(|file| file.passages.pop().map(|_| ()).unwrap()).is_err());
+        assert!(edited(|file| file.passages[0] ^= 1 << 1).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_text() {
+        let text = to_json(&samples().remove(0).1);
+        assert!(from_json(&text[..text.len() / 2]).is_err());
+        assert!(from_json("").is_err());
+    }
+}
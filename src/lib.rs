@@ -1,161 +1,114 @@
+pub mod ascii;
+pub mod batch;
+pub mod binary;
+pub mod bitmap;
+pub mod bitset;
+pub mod event_log;
+pub mod formats;
+pub mod generator;
+pub mod json;
+pub mod mask;
+pub mod maze;
+pub mod render;
+pub mod settings;
+pub mod solver;
+pub mod terminal;
+pub mod topology;
+pub mod tui;
+
 pub mod maze_maker {
+    use crate::bitset::BitSet;
+    use crate::event_log::{Event, EventLog, Replay};
+    use crate::generator::{self, Backtracker, Step};
+    use crate::maze::{Maze, Opening};
+    use crate::render::{self, Shape, Style};
+    use crate::settings::Settings;
+    use crate::topology::{CellId, Direction, FloorView, Vector2D};
     use ggez::{graphics, Context, GameResult};
-    use ggez::event::EventHandler;
-    use rand::Rng;
+    use ggez::event::{self, EventHandler, KeyCode, KeyMods};
+    use std::collections::HashSet;
     use std::fs::File;
-    use std::io::{Read};
+    use std::io::BufWriter;
+
+    impl From<Vector2D> for ggez::mint::Point2<f32> {
+        fn from(vector: Vector2D) -> Self {
+            ggez::mint::Point2::<f32> {
+                x: vector.x,
+                y: vector.y
+            }
+        }
+    }
 
-    #[derive(Clone)]
-    struct Vector2D {
-        x: f32,
-        y: f32
+    struct Viewport {
+        scale: f32,
+        offset: Vector2D
     }
 
-    impl Vector2D {
-        pub fn new(x: f32, y: f32) -> Self {
+    impl Viewport {
+        fn new(drawable_size: (f32, f32), size: Vector2D) -> Self {
+            let scale = (drawable_size.0 / size.x).min(drawable_size.1 / size.y);
             Self {
-                x,
-                y
+                scale,
+                offset: Vector2D::new((drawable_size.0 - size.x * scale) / 2.0, (drawable_size.1 - size.y * scale) / 2.0)
             }
         }
-    }
 
-    impl Into<ggez::mint::Point2<f32>> for Vector2D {
-        fn into(self) -> ggez::mint::Point2<f32> {
-            ggez::mint::Point2::<f32> {
-                x: self.x,
-                y: self.y
-            }
+        fn point(&self, point: Vector2D) -> Vector2D {
+            Vector2D::new(self.offset.x + point.x * self.scale, self.offset.y + point.y * self.scale)
         }
-    }
 
-    enum Position {
-        Right,
-        Down
+        fn points(&self, points: &[Vector2D]) -> Vec<Vector2D> {
+            points.iter().map(|point| self.point(*point)).collect()
+        }
     }
 
-    struct Edge {
-        mesh: graphics::Mesh
+    /// What moves the head: the generator itself, or a recorded generation played back.
+    enum Source {
+        Generator(Backtracker),
+        Replay(Replay)
     }
 
-    impl Edge {
-        fn new(mesh: graphics::Mesh) -> Self {
-            Self {
-                mesh
+    impl Source {
+        fn head(&self) -> Option<CellId> {
+            match self {
+                Source::Generator(backtracker) => backtracker.head(),
+                Source::Replay(replay) => replay.head()
+            }
+        }
+
+        fn is_finished(&self) -> bool {
+            match self {
+                Source::Generator(backtracker) => backtracker.is_finished(),
+                Source::Replay(replay) => replay.is_finished()
             }
         }
-    }
 
-    enum ComingFrom {
-        Up,
-        Right,
-        Down,
-        Left,
-        CanNotMove
+        fn step(&mut self, maze: &mut Maze) -> Step {
+            match self {
+                Source::Generator(backtracker) => backtracker.step(maze, &mut rand::thread_rng()),
+                Source::Replay(replay) => replay.step(maze)
+            }
+        }
     }
 
     struct HeadNode {
         mesh: graphics::Mesh,
-        taken_paths: Vec<[u8; 2]>,
-        cell_width: f32,
-        cell_height: f32,
-        cell_location: [u8; 2],
-        table: [u8; 2],
-        game_not_finished: bool
+        source: Source
     }
 
     impl HeadNode {
-        fn new(mesh: graphics::Mesh, taken_paths: Vec<[u8; 2]>, cell_width: f32, cell_height: f32, cell_location: [u8; 2], table: [u8; 2], game_not_finished: bool) -> Self {
+        fn new(mesh: graphics::Mesh, source: Source) -> Self {
             Self {
                 mesh,
-                taken_paths,
-                cell_width,
-                cell_height,
-                cell_location,
-                table,
-                game_not_finished
-            }
-        }
-
-        fn move_by_one_cell_randomly(&mut self, context: &mut Context, cells: &mut Vec<Vec<Cell>>) -> ComingFrom {
-            let mut coming_from: ComingFrom = ComingFrom::CanNotMove;
-            let (mut try_up, mut try_right, mut try_down, mut try_left) : (bool, bool, bool, bool) = (true, true, true, true);
-            loop {
-                let random_number = rand::thread_rng().gen_range(0..4);
-                if try_up || try_right || try_down || try_left {
-                    match random_number {
-                        0 => if self.cell_location[0] != 0 && cells.get(self.cell_location[1] as usize).unwrap().get((self.cell_location[0] - 1) as usize).unwrap().not_visited == true {
-                            self.cell_location[0] -= 1;
-                            self.mesh = self.recreate_circle_mesh(context, [0.5, 0.0, 0.0, 1.0]);
-                            coming_from = ComingFrom::Down;
-                            println!("PUSHING : {:?}", self.cell_location);
-                            self.taken_paths.push([self.cell_location[0], self.cell_location[1]]);
-                            cells.get_mut(self.cell_location[1] as usize).unwrap().get_mut((self.cell_location[0]) as usize).unwrap().not_visited = false;
-                            break;
-                        } else {
-                            try_up = false;
-                        },
-                        1 => if self.cell_location[1] != self.table[1] - 1 && cells.get((self.cell_location[1] + 1) as usize).unwrap().get(self.cell_location[0] as usize).unwrap().not_visited == true {
-                            self.cell_location[1] += 1;
-                            self.mesh = self.recreate_circle_mesh(context, [0.5, 0.0, 0.0, 1.0]);
-                            coming_from = ComingFrom::Left;
-                            println!("PUSHING : {:?}", self.cell_location);
-                            self.taken_paths.push([self.cell_location[0], self.cell_location[1]]);
-                            cells.get_mut(self.cell_location[1] as usize).unwrap().get_mut((self.cell_location[0]) as usize).unwrap().not_visited = false;
-                            break;
-                        } else {
-                            try_right = false;
-                        },
-                        2 => if self.cell_location[0] != self.table[0] - 1 && cells.get(self.cell_location[1] as usize).unwrap().get((self.cell_location[0] + 1) as usize).unwrap().not_visited == true {
-                            self.cell_location[0] += 1;
-                            self.mesh = self.recreate_circle_mesh(context, [0.5, 0.0, 0.0, 1.0]);
-                            coming_from = ComingFrom::Up;
-                            println!("PUSHING : {:?}", self.cell_location);
-                            self.taken_paths.push([self.cell_location[0], self.cell_location[1]]);
-                            cells.get_mut(self.cell_location[1] as usize).unwrap().get_mut((self.cell_location[0]) as usize).unwrap().not_visited = false;
-                            break;
-                        } else {
-                            try_down = false;
-                        },
-                        3 => if self.cell_location[1] != 0 && cells.get((self.cell_location[1] - 1) as usize).unwrap().get(self.cell_location[0] as usize).unwrap().not_visited == true {
-                            self.cell_location[1] -= 1;
-                            self.mesh = self.recreate_circle_mesh(context, [0.5, 0.0, 0.0, 1.0]);
-                            coming_from = ComingFrom::Right;
-                            println!("PUSHING : {:?}", self.cell_location);
-                            self.taken_paths.push([self.cell_location[0], self.cell_location[1]]);
-                            cells.get_mut(self.cell_location[1] as usize).unwrap().get_mut((self.cell_location[0]) as usize).unwrap().not_visited = false;
-                            break;
-                        } else {
-                            try_left = false;
-                        },
-                        _ => ()
-                    }
-                } else {
-                    break;
-                }
+                source
             }
-            coming_from
         }
 
-        fn move_back_by_one_cell(&mut self, context: &mut Context) {
-            if self.taken_paths.len() == 0 {
-                println!("FINISHED :D");
-                self.game_not_finished = false;
-                return;
-            }
-            let cell_location = self.taken_paths.pop().unwrap();
-            println!("POPPING : {:?}", self.cell_location);
-
-            self.cell_location[0] = cell_location[0];
-            self.cell_location[1] = cell_location[1];
-            self.mesh = self.recreate_circle_mesh(context, [0.5, 0.0, 0.0, 1.0]);
-        }
-
-        fn recreate_circle_mesh(&self, context: &mut Context, color: [f32; 4]) -> ggez::graphics::Mesh {
+        fn recreate_circle_mesh(context: &mut Context, center: Vector2D, color: [f32; 4]) -> ggez::graphics::Mesh {
             graphics::Mesh::new_circle(
                 context,
                 graphics::DrawMode::stroke(2.0),
-                Vector2D::new(self.cell_location[1] as f32 * self.cell_width + self.cell_width / 2.0, self.cell_location[0] as f32 * self.cell_height + self.cell_height / 2.0),
+                center,
                 6.0,
                 0.5,
                 graphics::Color::new(color[0], color[1], color[2], color[3])
@@ -163,289 +116,280 @@ pub mod maze_maker {
         }
     }
 
-    struct Cell {
-        mesh: graphics::Mesh,
-        not_visited: bool
+    /// How wide walls are drawn, in pixels.
+    const WALL_WIDTH: f32 = 5.0;
+
+    /// How many consecutive cells share one pair of meshes.
+    const CHUNK_CELLS: usize = 1024;
+
+    /// The cells of a block of consecutive ids, batched into one mesh for their fills and one
+    /// for their walls and markers. Walls are drawn after every chunk's fills so no neighbouring
+    /// cell paints over them. A chunk is only rebuilt when something in it changed.
+    struct Chunk {
+        cells: Option<graphics::Mesh>,
+        walls: Option<graphics::Mesh>,
+        dirty: bool
     }
-    
-    impl Cell {
-        fn new(mesh: graphics::Mesh, not_visited: bool) -> Self {
+
+    impl Chunk {
+        fn new() -> Self {
             Self {
-                mesh,
-                not_visited
+                cells: None,
+                walls: None,
+                dirty: true
             }
         }
     }
 
+    fn color(color: [f32; 4]) -> graphics::Color {
+        graphics::Color::new(color[0], color[1], color[2], color[3])
+    }
+
     pub struct Game {
-        cells: Vec<Vec<Cell>>,
-        edges: Vec<Vec<Edge>>,
+        maze: Maze,
+        lit: BitSet,
+        chunks: Vec<Chunk>,
         head_node: HeadNode,
+        viewport: Viewport,
+        visible_floor: Option<usize>,
+        entrance: Opening,
+        exit: Opening,
+        openings_made: bool,
+        event_log: Option<EventLog<BufWriter<File>>>,
         current_milisec: u64,
         next_milisec: u64,
+        background_color: [f32; 4],
         foreground_color: [f32; 4],
         refresh_rate_in_miliseconds: u64
     }
 
     impl Game {
-        pub fn new(context: &mut Context) -> Self {
-            let mut settings = String::new();
-            File::open("./settings.conf").expect("Error opening the settings.conf file").read_to_string(&mut settings).expect("Error reading to string");
-
-            let settings_rows = settings.split('\n').collect::<Vec<&str>>();
-            
-            let table_size = settings_rows.get(0)
-                .unwrap()
-                .split(':').collect::<Vec<&str>>().get(1).unwrap().split('x').collect::<Vec<&str>>();
-            
-            let mut table: [u8; 2] = [0; 2];
-            table[0] = table_size.get(0).unwrap().parse::<u8>().unwrap();
-            table[1] = table_size.get(1).unwrap().parse::<u8>().unwrap();
-
-            let background_color = settings_rows.get(1)
-                .unwrap()
-                .split(':')
-                .collect::<Vec<&str>>()
-                .get(1).unwrap().split(',').collect::<Vec<&str>>();
-            let background_color: [f32; 4] = [background_color.get(0).unwrap().parse::<f32>().unwrap(), background_color.get(1).unwrap().parse::<f32>().unwrap(), background_color.get(2).unwrap().parse::<f32>().unwrap(), background_color.get(3).unwrap().parse::<f32>().unwrap()];
-
-            let foreground_color = settings_rows.get(2)
-                .unwrap()
-                .split(':')
-                .collect::<Vec<&str>>()
-                .get(1).unwrap().split(',').collect::<Vec<&str>>();
-            let foreground_color: [f32; 4] = [foreground_color.get(0).unwrap().parse::<f32>().unwrap(), foreground_color.get(1).unwrap().parse::<f32>().unwrap(), foreground_color.get(2).unwrap().parse::<f32>().unwrap(), foreground_color.get(3).unwrap().parse::<f32>().unwrap()];
-
-            let refresh_rate_in_miliseconds = settings_rows
-                .get(3)
-                .unwrap()
-                .split(':')
-                .collect::<Vec<&str>>();
-            let refresh_rate_in_miliseconds = refresh_rate_in_miliseconds.get(1).unwrap();
-            let refresh_rate_in_miliseconds: u64 = refresh_rate_in_miliseconds.parse::<u64>().unwrap();
-
-            let wait_then_solve_in_miliseconds = settings_rows
-                .get(4)
-                .unwrap()
-                .split(':')
-                .collect::<Vec<&str>>();
-            let wait_then_solve_in_miliseconds = wait_then_solve_in_miliseconds.get(1).unwrap();
-            let wait_then_solve_in_miliseconds: u64 = wait_then_solve_in_miliseconds.parse::<u64>().unwrap();
-
-            let (mut cell_width, mut cell_height) = graphics::drawable_size(context);
-            cell_width /= table[1] as f32;
-            cell_height /= table[0] as f32;
-
-            let taken_paths: Vec<[u8; 2]> = Vec::new();
-            let mut cells: Vec<Vec<Cell>> = Vec::new();
-            let mut edges: Vec<Vec<Edge>> = Vec::new();
-
-            let mut cell_rect = graphics::Rect::new(0.0, 0.0, cell_width, cell_height);
-
-            for i in 0..table[1] as usize {
-                cells.push(Vec::new());
-                edges.push(Vec::new());
-                for j in 0..table[0] as usize {
-                    let (x, y) = (i as f32 * cell_width, j as f32 * cell_height);
-                    cell_rect.x = x;
-                    cell_rect.y = y;
-                    cells.get_mut(i).unwrap().push(Cell::new(
-                        graphics::Mesh::new_rectangle(
-                            context,
-                            graphics::DrawMode::fill(),
-                            cell_rect,
-                            graphics::Color::new(background_color[0], background_color[1], background_color[2], background_color[3])
-                        ).unwrap(),
-                        true
-                    ));
-                    edges.get_mut(i).unwrap().push(Edge::new(
-                        graphics::Mesh::new_line(
-                            context,
-                            &[Vector2D::new(x + cell_width, y), Vector2D::new(x + cell_width, y + cell_height)],
-                            5.0,
-                            graphics::Color::BLACK).unwrap()
-                    ));
-                    edges.get_mut(i).unwrap().push(Edge::new(
-                        graphics::Mesh::new_line(
-                            context,
-                            &[Vector2D::new(x, y + cell_height), Vector2D::new(x + cell_width, y + cell_height)],
-                            5.0,
-                            graphics::Color::BLACK).unwrap()
-                    ));
+        pub fn new(context: &mut Context) -> Result<Self, String> {
+            Self::with_settings(context, Settings::load("./settings.conf")?)
+        }
+
+        pub fn with_settings(context: &mut Context, settings: Settings) -> Result<Self, String> {
+            let mut maze = Maze::new(settings.build_topology()?);
+            settings.entrance.check(maze.topology()).map_err(|error| format!("Invalid entrance: {}", error))?;
+            settings.exit.check(maze.topology()).map_err(|error| format!("Invalid exit: {}", error))?;
+
+            let start = generator::random_start(maze.topology(), &mut rand::thread_rng());
+            let mut backtracker = Backtracker::new(&maze, start, settings.weave);
+            let mut event_log = settings.event_log.as_ref().map(|path| EventLog::create(path, &maze, start, generator::name(settings.weave), None)).transpose()?;
+            if settings.instant {
+                match event_log.as_mut() {
+                    Some(event_log) => {
+                        while !backtracker.is_finished() {
+                            let step = backtracker.step(&mut maze, &mut rand::thread_rng());
+                            event_log.record_step(&step)?;
+                        }
+                    },
+                    None => println!("{}", backtracker.run(&mut maze, &mut rand::thread_rng()))
+                }
+            }
+            let instant = settings.instant;
+            let mut game = Self::build(context, maze, start, Source::Generator(backtracker), settings);
+            game.event_log = event_log;
+            if instant {
+                for cell in 0..game.maze.topology().cell_count() {
+                    if game.maze.topology().is_included(cell) {
+                        game.lit.insert(cell);
+                    }
                 }
             }
+            Ok(game)
+        }
+
+        /// Plays back the generation recorded in an event log instead of generating a new maze,
+        /// with the colours and pace of the settings.
+        pub fn replay(context: &mut Context, settings: Settings, path: &str) -> Result<Self, String> {
+            let (maze, replay) = Replay::load(path)?;
+            let start = replay.start();
+            Ok(Self::build(context, maze, start, Source::Replay(replay), settings))
+        }
 
-            let random_cell_index_1 = rand::thread_rng().gen_range(0..table[0]);
-            let random_cell_index_2 = rand::thread_rng().gen_range(0..table[1]);
+        fn build(context: &mut Context, maze: Maze, start: CellId, source: Source, settings: Settings) -> Self {
+            let viewport = Viewport::new(graphics::drawable_size(context), maze.topology().size());
+            let cell_count = maze.topology().cell_count();
+            let chunks = (0..cell_count.div_ceil(CHUNK_CELLS)).map(|_| Chunk::new()).collect::<Vec<Chunk>>();
 
             let head_node = HeadNode::new(
-                graphics::Mesh::new_circle(
-                    context,
-                    graphics::DrawMode::stroke(2.0),
-                    Vector2D::new(random_cell_index_2 as f32 * cell_width + cell_width / 2.0, random_cell_index_1 as f32 * cell_height + cell_height / 2.0),
-                    6.0,
-                    0.5,
-                    graphics::Color::new(0.5, 0.0, 0.0, 1.0)).unwrap(),
-                taken_paths,
-                cell_width,
-                cell_height,
-                [random_cell_index_1, random_cell_index_2],
-                table,
-                true
+                HeadNode::recreate_circle_mesh(context, viewport.point(maze.topology().cell_center(start)), [0.5, 0.0, 0.0, 1.0]),
+                source
             );
-
-            cell_rect.x = random_cell_index_2 as f32 * cell_width;
-            cell_rect.y = random_cell_index_1 as f32 * cell_height;
-            cells.get_mut(random_cell_index_2 as usize).unwrap().get_mut(random_cell_index_1 as usize).unwrap().mesh = graphics::Mesh::new_rectangle(
-                context,
-                graphics::DrawMode::fill(),
-                cell_rect,
-                graphics::Color::new(foreground_color[0], foreground_color[1], foreground_color[2], foreground_color[3])
-            ).unwrap();
-            cells.get_mut(random_cell_index_2 as usize).unwrap().get_mut(random_cell_index_1 as usize).unwrap().not_visited = false;
-
-            Self {
-                cells,
-                edges,
+            let visible_floor = if maze.topology().floor_count() > 1 && maze.topology().layout().floor_view == FloorView::Single {
+                Some(maze.topology().floor(start))
+            } else {
+                None
+            };
+
+            let mut game = Self {
+                maze,
+                lit: BitSet::new(cell_count),
+                chunks,
                 head_node,
-                current_milisec: wait_then_solve_in_miliseconds,
+                viewport,
+                visible_floor,
+                entrance: settings.entrance,
+                exit: settings.exit,
+                openings_made: false,
+                event_log: None,
+                current_milisec: settings.wait_then_solve_in_miliseconds,
                 next_milisec: 0,
-                foreground_color,
-                refresh_rate_in_miliseconds
+                background_color: settings.background_color,
+                foreground_color: settings.foreground_color,
+                refresh_rate_in_miliseconds: settings.refresh_rate_in_miliseconds
+            };
+            game.light_up_cell(start);
+            game
+        }
+
+        fn is_visible(&self, cell: CellId) -> bool {
+            self.visible_floor.is_none_or(|floor| self.maze.topology().floor(cell) == floor)
+        }
+
+        fn mark_dirty(&mut self, cell: CellId) {
+            self.chunks[cell / CHUNK_CELLS].dirty = true;
+        }
+
+        fn mark_all_dirty(&mut self) {
+            for chunk in self.chunks.iter_mut() {
+                chunk.dirty = true;
+            }
+        }
+
+        fn rebuild_chunk(&mut self, context: &mut Context, chunk: usize) {
+            let topology = self.maze.topology();
+            let first = chunk * CHUNK_CELLS;
+            let cells = (first..(first + CHUNK_CELLS).min(topology.cell_count()))
+                .filter(|cell| topology.is_included(*cell) && self.is_visible(*cell))
+                .collect::<HashSet<CellId>>();
+            // Laid out in layout units, with walls as wide in pixels whatever the scale.
+            let style = Style {
+                cell_size: 1.0,
+                margin: 0.0,
+                wall_thickness: WALL_WIDTH / self.viewport.scale,
+                background_color: self.background_color,
+                foreground_color: self.foreground_color,
+                wall_color: [0.0, 0.0, 0.0, 1.0],
+                solution: false,
+                openings: false,
+                heatmap: false
+            };
+            let scene = render::window_scene(&self.maze, &style, &self.lit, &cells);
+            let (fills, walls) = scene.layers.split_first().unwrap();
+            let mut fill_builder = graphics::MeshBuilder::new();
+            self.add_shapes(&mut fill_builder, &fills.shapes);
+            let mut wall_builder = graphics::MeshBuilder::new();
+            for layer in walls {
+                self.add_shapes(&mut wall_builder, &layer.shapes);
+            }
+            let chunk = &mut self.chunks[chunk];
+            chunk.cells = fill_builder.build(context).ok();
+            chunk.walls = wall_builder.build(context).ok();
+            chunk.dirty = false;
+        }
+
+        /// Adds shapes laid out in layout units to a mesh, placed in the window.
+        fn add_shapes(&self, builder: &mut graphics::MeshBuilder, shapes: &[Shape]) {
+            let scale = self.viewport.scale;
+            for shape in shapes {
+                match shape {
+                    Shape::Polygon { points, color: fill } => builder.polygon(graphics::DrawMode::fill(), &self.viewport.points(points), color(*fill)),
+                    Shape::Line { points, width, color: stroke } => builder.line(&self.viewport.points(points), width * scale, color(*stroke)),
+                    Shape::Circle { center, radius, color: fill } => builder.circle(graphics::DrawMode::fill(), self.viewport.point(*center), radius * scale, 0.5, color(*fill))
+                }.unwrap();
             }
         }
 
         fn draw_objects(&mut self, context: &mut Context) {
-            for pointer_to_cell in self.cells.iter() {
-                for cell in pointer_to_cell.iter() {
-                    graphics::draw(context, &cell.mesh, graphics::DrawParam::default()).expect("Error in drawing meshes for cells")
+            for chunk in 0..self.chunks.len() {
+                if self.chunks[chunk].dirty {
+                    self.rebuild_chunk(context, chunk);
                 }
             }
-            for pointer_to_edge in self.edges.iter() {
-                for edge in pointer_to_edge.iter() {
-                    graphics::draw(context, &edge.mesh, graphics::DrawParam::default()).expect("Error in drawing meshes for edges");
+            for mesh in self.chunks.iter().filter_map(|chunk| chunk.cells.as_ref()) {
+                graphics::draw(context, mesh, graphics::DrawParam::default()).expect("Error in drawing meshes for cells");
+            }
+            for mesh in self.chunks.iter().filter_map(|chunk| chunk.walls.as_ref()) {
+                graphics::draw(context, mesh, graphics::DrawParam::default()).expect("Error in drawing meshes for walls");
+            }
+            if let Some(head) = self.head_node.source.head() {
+                if self.is_visible(head) {
+                    graphics::draw(context, &self.head_node.mesh, graphics::DrawParam::default()).expect("Error in drawing meshe for head node");
                 }
             }
-            graphics::draw(context, &self.head_node.mesh, graphics::DrawParam::default()).expect("Error in drawing meshe for head node");
         }
 
-        fn recreate_rectangle_mesh(&self, context: &mut Context, color: [f32; 4], cell_location: [u8; 2]) -> ggez::graphics::Mesh {
-            graphics::Mesh::new_rectangle(
-                context,
-                graphics::DrawMode::fill(),
-                graphics::Rect::new(cell_location[1] as f32 * self.head_node.cell_width, cell_location[0] as f32 * self.head_node.cell_height, self.head_node.cell_width, self.head_node.cell_height),
-                graphics::Color::new(color[0], color[1], color[2], color[3])
-            ).unwrap()
+        fn light_up_cell(&mut self, cell: CellId) {
+            self.lit.insert(cell);
+            self.mark_dirty(cell);
         }
 
-        fn recreate_line_mesh(&self, context: &mut Context, cell_location: [u8; 2], position: Position) -> ggez::graphics::Mesh {
-            match position {
-                Position::Right => {
-                    graphics::Mesh::new_line(
-                        context,
-                        &[Vector2D::new(
-                            cell_location[0] as f32 * self.head_node.cell_width + self.head_node.cell_width,
-                            cell_location[1] as f32 * self.head_node.cell_height
-                        ),
-                        Vector2D::new(
-                            cell_location[0] as f32 * self.head_node.cell_width + self.head_node.cell_width,
-                            cell_location[1] as f32 * self.head_node.cell_height + self.head_node.cell_height
-                        )],
-                        5.0,
-                        graphics::Color::new(self.foreground_color[0], self.foreground_color[1], self.foreground_color[2], self.foreground_color[3])
-                    ).unwrap()
-                },
-                Position::Down => {
-                    graphics::Mesh::new_line(
-                        context,
-                        &[Vector2D::new(
-                            cell_location[0] as f32 * self.head_node.cell_width,
-                            cell_location[1] as f32 * self.head_node.cell_height + self.head_node.cell_height
-                        ),
-                        Vector2D::new(
-                            cell_location[0] as f32 * self.head_node.cell_width + self.head_node.cell_width,
-                            cell_location[1] as f32 * self.head_node.cell_height + self.head_node.cell_height
-                        )],
-                        5.0,
-                        graphics::Color::new(self.foreground_color[0], self.foreground_color[1], self.foreground_color[2], self.foreground_color[3])
-                    ).unwrap()
-                }
+        fn move_head_node(&mut self, context: &mut Context, cell: CellId) {
+            self.head_node.mesh = HeadNode::recreate_circle_mesh(context, self.viewport.point(self.maze.topology().cell_center(cell)), [0.5, 0.0, 0.0, 1.0]);
+            if self.visible_floor.is_some_and(|floor| floor != self.maze.topology().floor(cell)) {
+                self.visible_floor = Some(self.maze.topology().floor(cell));
+                self.mark_all_dirty();
             }
         }
 
-        fn remove_edge_and_light_up_cell(&mut self, context : &mut Context, coming_from: ComingFrom) {
-            let mut previous_cell_location: [u8; 2] = [0; 2];
-            match coming_from {
-                ComingFrom::Up => {
-                    previous_cell_location[0] = self.head_node.cell_location[0] - 1;
-                    previous_cell_location[1] = self.head_node.cell_location[1];
-                    let equvalent_cell_x = previous_cell_location[1];
-                    let equvalent_cell_y = previous_cell_location[0] * 2 + 1;
-                    self.edges.get_mut(equvalent_cell_x as usize)
-                        .unwrap()
-                        .get_mut(equvalent_cell_y as usize)
-                        .unwrap()
-                        .mesh = self.recreate_line_mesh(context, [equvalent_cell_x, equvalent_cell_y], Position::Down);
-                    self.cells.get_mut(previous_cell_location[1] as usize).unwrap().get_mut(previous_cell_location[0] as usize).unwrap().not_visited = false;
-                },
-                ComingFrom::Right => {
-                    previous_cell_location[0] = self.head_node.cell_location[0];
-                    previous_cell_location[1] = self.head_node.cell_location[1];
-                    let equvalent_cell_x = previous_cell_location[1];
-                    let equvalent_cell_y = previous_cell_location[0] * 2;
-                    self.edges.get_mut(equvalent_cell_x as usize)
-                        .unwrap()
-                        .get_mut(equvalent_cell_y as usize)
-                        .unwrap()
-                        .mesh = self.recreate_line_mesh(context, [equvalent_cell_x, equvalent_cell_y], Position::Right);
-                    self.cells.get_mut(previous_cell_location[1] as usize).unwrap().get_mut(previous_cell_location[0] as usize).unwrap().not_visited = false;
-                },
-                ComingFrom::Down => {
-                    previous_cell_location[0] = self.head_node.cell_location[0];
-                    previous_cell_location[1] = self.head_node.cell_location[1];
-                    let equvalent_cell_x = previous_cell_location[1];
-                    let equvalent_cell_y = previous_cell_location[0] * 2 + 1;
-                    self.edges.get_mut(equvalent_cell_x as usize)
-                        .unwrap()
-                        .get_mut(equvalent_cell_y as usize)
-                        .unwrap()
-                        .mesh = self.recreate_line_mesh(context, [equvalent_cell_x, equvalent_cell_y], Position::Down);
-                    self.cells.get_mut(previous_cell_location[1] as usize).unwrap().get_mut(previous_cell_location[0] as usize).unwrap().not_visited = false;
-                },
-                ComingFrom::Left => {
-                    previous_cell_location[0] = self.head_node.cell_location[0];
-                    previous_cell_location[1] = self.head_node.cell_location[1] - 1;
-                    let equvalent_cell_x = previous_cell_location[1];
-                    let equvalent_cell_y = previous_cell_location[0] * 2;
-                    self.edges.get_mut(equvalent_cell_x as usize)
-                        .unwrap()
-                        .get_mut(equvalent_cell_y as usize)
-                        .unwrap()
-                        .mesh = self.recreate_line_mesh(context, [equvalent_cell_x, equvalent_cell_y], Position::Right);
-                    self.cells.get_mut(previous_cell_location[1] as usize).unwrap().get_mut(previous_cell_location[0] as usize).unwrap().not_visited = false;
-                },
-                ComingFrom::CanNotMove => {
-                    self.head_node.move_back_by_one_cell(context);
-                }
+        /// Marks the cells on both sides of a wall for redrawing once it is carved.
+        fn remove_edge(&mut self, cell: CellId, direction: Direction) {
+            self.mark_dirty(cell);
+            if let Some(neighbour) = self.maze.topology().neighbour(cell, direction) {
+                self.mark_dirty(neighbour);
+            }
+        }
+
+        /// Writes to the event log if there is one, giving up on it after the first error rather
+        /// than stopping the animation.
+        fn record(&mut self, write: impl FnOnce(&mut EventLog<BufWriter<File>>) -> Result<(), String>) {
+            if let Some(Err(error)) = self.event_log.as_mut().map(write) {
+                eprintln!("{}", error);
+                self.event_log = None;
             }
-            self.cells.get_mut(self.head_node.cell_location[1] as usize)
-                .unwrap()
-                .get_mut(self.head_node.cell_location[0] as usize)
-                .unwrap()
-                .mesh = self.recreate_rectangle_mesh(context, [self.foreground_color[0], self.foreground_color[1], self.foreground_color[2], self.foreground_color[3]], self.head_node.cell_location);
         }
 
         fn update_objects(&mut self, context: &mut Context) {
-            if self.head_node.game_not_finished {
+            if !self.head_node.source.is_finished() {
                 self.next_milisec = ggez::timer::time_since_start(context).as_millis() as u64;
                 if self.current_milisec < self.next_milisec {
                     self.current_milisec = self.next_milisec + self.refresh_rate_in_miliseconds;
-                    let coming_from = self.head_node.move_by_one_cell_randomly(context, &mut self.cells);
-                    self.remove_edge_and_light_up_cell(
-                        context,
-                        coming_from
-                    );
+                    let step = self.head_node.source.step(&mut self.maze);
+                    self.record(|event_log| event_log.record_step(&step));
+                    match step {
+                        Step::Pushed { from, direction, to } => {
+                            self.remove_edge(from, direction);
+                            self.light_up_cell(to);
+                            self.move_head_node(context, to);
+                        },
+                        Step::Tunnelled { from, direction, under, to } => {
+                            self.remove_edge(from, direction);
+                            self.remove_edge(under, self.maze.crossing(under).unwrap());
+                            self.light_up_cell(to);
+                            self.move_head_node(context, to);
+                        },
+                        Step::Popped { to } => self.move_head_node(context, to),
+                        Step::Jumped { to } => {
+                            self.light_up_cell(to);
+                            self.move_head_node(context, to);
+                        },
+                        Step::Finished => ()
+                    }
+                }
+            } else if !self.openings_made {
+                let openings = match &self.head_node.source {
+                    Source::Generator(_) => self.maze.make_openings(self.entrance, self.exit, &mut rand::thread_rng()),
+                    Source::Replay(replay) => replay.openings()
+                };
+                if let Some((entrance, exit)) = openings {
+                    self.maze.set_openings(entrance, exit);
+                    self.record(|event_log| event_log.record(Event::Openings { entrance, exit }));
+                    self.remove_edge(entrance.0, entrance.1);
+                    self.remove_edge(exit.0, exit.1);
                 }
+                self.openings_made = true;
             }
         }
     }
@@ -456,6 +400,29 @@ pub mod maze_maker {
             Ok(())
         }
 
+        fn key_down_event(&mut self, context: &mut Context, keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
+            let floor_count = self.maze.topology().floor_count();
+            let visible_floor = self.visible_floor;
+            match (keycode, self.visible_floor) {
+                (KeyCode::Escape, _) => event::quit(context),
+                (KeyCode::Up, Some(floor)) | (KeyCode::PageUp, Some(floor)) => self.visible_floor = Some((floor + 1).min(floor_count - 1)),
+                (KeyCode::Down, Some(floor)) | (KeyCode::PageDown, Some(floor)) => self.visible_floor = Some(floor.saturating_sub(1)),
+                _ => ()
+            }
+            if self.visible_floor != visible_floor {
+                self.mark_all_dirty();
+            }
+        }
+
+        fn resize_event(&mut self, context: &mut Context, width: f32, height: f32) {
+            graphics::set_screen_coordinates(context, graphics::Rect::new(0.0, 0.0, width, height)).expect("Error in resizing the screen");
+            self.viewport = Viewport::new((width, height), self.maze.topology().size());
+            if let Some(head) = self.head_node.source.head() {
+                self.head_node.mesh = HeadNode::recreate_circle_mesh(context, self.viewport.point(self.maze.topology().cell_center(head)), [0.5, 0.0, 0.0, 1.0]);
+            }
+            self.mark_all_dirty();
+        }
+
         fn draw(&mut self, context: &mut Context) -> GameResult<()> {
             graphics::clear(context, graphics::Color::WHITE);
 
@@ -464,4 +431,4 @@ pub mod maze_maker {
             graphics::present(context)
         }
     }
-}
\ No newline at end of file
+}
@@ -1,17 +1,574 @@
-use ggez::{graphics, ContextBuilder, GameResult};
+use ggez::{graphics, ContextBuilder};
 use ggez::event;
+use maze_maker::formats::{self, Format};
+use maze_maker::maze_maker::Game;
+use maze_maker::render::{GenerationGif, Style};
+use maze_maker::settings::Settings;
+use maze_maker::batch::{self, Generation};
+use maze_maker::event_log::{Event, EventLog};
+use maze_maker::{generator, solver, tui};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::Instant;
 
-mod lib;
+const USAGE: &str = "\
+Usage: maze_maker [command] [options]
 
-fn main() -> GameResult<()> {
-    let (mut context, event_loop) = ContextBuilder::new("maze_maker", "Amirhosein_GPR").build().expect("Error extracting GameResult in ContextBuilder");
+Commands:
+  generate   Generate a maze and write it out
+  solve      Find the way through a maze
+  render     Write a maze out in another format
+  gui        Animate generating a maze in a window, the default without a command
+  tui        Animate generating and solving a maze in the terminal
+  batch      Generate many mazes at once
+  help       Show the options of a command
 
-    let game_state = lib::maze_maker::Game::new(&mut context);
+Run `maze_maker <command> --help` for the options of each command.
 
-    graphics::set_resizable(&mut context, true)?;
-    graphics::set_drawable_size(&mut context, 720.0, 720.0)?;
+Exit codes: 0 on success, 1 when the command fails, 2 for invalid arguments.
+";
 
-    graphics::set_window_title(&mut context, "Maze maker");
+const MAZE_OPTIONS: &str = "\
+Maze options, each overriding the settings file:
+  --settings <path>         Settings file to start from (default ./settings.conf, if present)
+  --size <rows>x<columns>   Table size
+  --topology <kind>         rectangular, hexagonal, triangular or polar
+  --wrap <wrap>             none, cylinder, torus, mobius or klein
+  --floors <count>          Floors stacked on top of each other
+  --mask <path>             Image or text file marking the cells to keep
+  --algorithm <name>        recursive_backtracker or recursive_backtracker_weave
+  --entrance <opening>      <side>,<index>, random or farthest
+  --exit <opening>          <side>,<index>, random or farthest
+  --set <key>:<value>       Any other line of the settings file, may be repeated
+";
+
+const STYLE_OPTIONS: &str = "\
+Style options for drawn formats:
+  --settings <path>         Settings file to take colors and sizes from (default ./settings.conf, if present)
+  --set <key>:<value>       Any line of the settings file, may be repeated
+";
+
+/// Maze options that stand for a line of the settings file.
+const SETTING_OPTIONS: [(&str, &str); 8] = [
+    ("size", "table_size"),
+    ("topology", "topology"),
+    ("wrap", "wrap"),
+    ("floors", "floors"),
+    ("mask", "mask"),
+    ("entrance", "entrance"),
+    ("exit", "exit"),
+    ("events", "event_log")
+];
+const MAZE_OPTION_NAMES: [&str; 10] = ["settings", "size", "topology", "wrap", "floors", "mask", "algorithm", "entrance", "exit", "set"];
+const STYLE_OPTION_NAMES: [&str; 2] = ["settings", "set"];
+
+/// A subcommand: its help, the options it takes besides the shared ones, and how many
+/// arguments it takes that are not options.
+struct Command {
+    name: &'static str,
+    usage: &'static str,
+    shared_help: &'static str,
+    options: &'static [&'static str],
+    shared_options: &'static [&'static str],
+    positional: usize
+}
+
+const GENERATE: Command = Command {
+    name: "generate",
+    usage: "\
+Usage: maze_maker generate [options]
+
+Generates a maze and writes it out.
+
+Options:
+  -o, --output <path>       Where to write the maze, - for standard output (default -)
+  -f, --format <format>     json, binary, ascii, terminal, svg, png or gif (default: from the
+                            output's extension, otherwise json)
+  --seed <number>           Seed for the random generator, the same seed and options always
+                            give the same maze (default: random)
+  --events <path>           Also log every step of the generation as JSON lines
+",
+    shared_help: MAZE_OPTIONS,
+    options: &["output", "format", "seed", "events"],
+    shared_options: &MAZE_OPTION_NAMES,
+    positional: 0
+};
+
+const SOLVE: Command = Command {
+    name: "solve",
+    usage: "\
+Usage: maze_maker solve [options] [input]
+
+Reads a maze from a file, or from standard input without one or for -, and finds the shortest
+way from the entrance to the exit. Mazes can be JSON, binary, ASCII art or PNG and BMP images.
+Fails when the maze has no entrance and exit, or no way between them.
+
+Options:
+  -o, --output <path>       Where to write the solution, - for standard output (default -)
+  -f, --format <format>     cells for the numbers of the cells on the way, one per line, or
+                            terminal, svg or png to draw the maze with the way through it
+                            (default: from the output's extension, otherwise cells)
+",
+    shared_help: STYLE_OPTIONS,
+    options: &["output", "format"],
+    shared_options: &STYLE_OPTION_NAMES,
+    positional: 1
+};
+
+const RENDER: Command = Command {
+    name: "render",
+    usage: "\
+Usage: maze_maker render [options] [input]
+
+Reads a maze from a file, or from standard input without one or for -, and writes it in another
+format. Mazes can be read from JSON, binary, ASCII art or PNG and BMP images.
+
+Options:
+  -o, --output <path>       Where to write the maze, - for standard output (default -)
+  -f, --format <format>     json, binary, ascii, terminal, svg or png (default: from the output's
+                            extension, otherwise json)
+",
+    shared_help: STYLE_OPTIONS,
+    options: &["output", "format"],
+    shared_options: &STYLE_OPTION_NAMES,
+    positional: 1
+};
+
+const GUI: Command = Command {
+    name: "gui",
+    usage: "\
+Usage: maze_maker gui [options]
+
+Opens a window and animates generating a maze, then cuts the entrance and exit. Escape closes it.
+
+Options:
+  --events <path>           Log every step of the generation as JSON lines
+  --replay <path>           Play back a log written with --events instead of generating
+",
+    shared_help: MAZE_OPTIONS,
+    options: &["events", "replay"],
+    shared_options: &MAZE_OPTION_NAMES,
+    positional: 0
+};
+
+const TUI: Command = Command {
+    name: "tui",
+    usage: "\
+Usage: maze_maker tui [options]
+
+Animates generating a maze in the terminal, one step every refresh_rate_in_miliseconds, then
+walks the way through it when show_solution is set. Only rectangular mazes that do not
+wrap around can be drawn.
+
+Keys:
+  space, p                  Pause or resume
+  n, right arrow            Take one step while paused
+  +, -                      Speed up or slow down
+  q, escape                 Quit
+
+Options:
+  --seed <number>           Seed for the random generator, the same seed and options always
+                            give the same maze (default: random)
+",
+    shared_help: MAZE_OPTIONS,
+    options: &["seed"],
+    shared_options: &MAZE_OPTION_NAMES,
+    positional: 0
+};
+
+const BATCH: Command = Command {
+    name: "batch",
+    usage: "\
+Usage: maze_maker batch [options] <count> [master_seed] [threads]
+
+Generates <count> mazes without a window and prints the index, seed and a fingerprint of the
+walls of each one. Maze i is always generated from the same seed, mixed from the master seed
+(default 0), whatever the number of threads (default: one per processor).
+
+With --output-dir, writes the mazes into that directory instead, <count> for every --size and
+--algorithm given, and a manifest listing the seed, fingerprint, algorithm, dimensions, cell count,
+dead ends, junctions, crossings and solution length of each file.
+
+Options:
+  -d, --output-dir <path>   Directory to write the mazes and the manifest into
+  -f, --format <format>     json, binary, ascii, terminal, svg, png or gif (default json)
+  --manifest <format>       csv or json, written as manifest.csv or manifest.json (default csv)
+  --size, --algorithm       May be repeated with --output-dir
+",
+    shared_help: MAZE_OPTIONS,
+    options: &["output-dir", "format", "manifest"],
+    shared_options: &MAZE_OPTION_NAMES,
+    positional: 3
+};
+
+const COMMANDS: [&Command; 6] = [&GENERATE, &SOLVE, &RENDER, &GUI, &TUI, &BATCH];
+
+/// The arguments after the command: options by name in the order given, and the rest.
+struct Arguments {
+    options: Vec<(String, String)>,
+    positional: Vec<String>
+}
+
+impl Arguments {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(option, _)| option == name).map(|(_, value)| value.as_str())
+    }
+
+    fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options.iter().filter(move |(option, _)| option == name).map(|(_, value)| value.as_str())
+    }
+}
+
+fn main() {
+    let arguments = env::args().skip(1).collect::<Vec<String>>();
+    let (command, rest) = match arguments.first().map(String::as_str) {
+        None => (&GUI, &arguments[..0]),
+        Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            return;
+        },
+        Some("help") => {
+            match arguments.get(1) {
+                Some(name) => print_help(find_command(name)),
+                None => print!("{}", USAGE)
+            }
+            return;
+        },
+        Some(name) if name.starts_with('-') => (&GUI, &arguments[..]),
+        Some(name) => (find_command(name), &arguments[1..])
+    };
+    let arguments = parse_arguments(command, rest);
+    match command.name {
+        "generate" => run_generate(&arguments),
+        "solve" => run_solve(&arguments),
+        "render" => run_render(&arguments),
+        "gui" => run_gui(&arguments),
+        "tui" => run_tui(&arguments),
+        _ => run_batch(&arguments)
+    }
+}
+
+fn find_command(name: &str) -> &'static Command {
+    COMMANDS.iter().copied().find(|command| command.name == name).unwrap_or_else(|| usage_error(None, &format!("Unknown command \"{}\"", name)))
+}
+
+fn print_help(command: &Command) {
+    print!("{}\n{}", command.usage, command.shared_help);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn usage_error(command: Option<&Command>, message: &str) -> ! {
+    eprintln!("{}", message);
+    match command {
+        Some(command) => eprintln!("Run `maze_maker {} --help` for its options.", command.name),
+        None => eprintln!("Run `maze_maker --help` for the commands.")
+    }
+    process::exit(2);
+}
+
+fn parse_arguments(command: &Command, arguments: &[String]) -> Arguments {
+    let known = |name: &str| command.options.contains(&name) || command.shared_options.contains(&name);
+    let mut parsed = Arguments {
+        options: Vec::new(),
+        positional: Vec::new()
+    };
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let (name, inline_value) = match argument.as_str() {
+            "-h" | "--help" => {
+                print_help(command);
+                process::exit(0);
+            },
+            "-o" => ("output", None),
+            "-f" => ("format", None),
+            "-d" => ("output-dir", None),
+            option if option.starts_with("--") => match option[2..].split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (&option[2..], None)
+            },
+            option if option.starts_with('-') && option != "-" => usage_error(Some(command), &format!("Unknown option \"{}\"", option)),
+            _ => {
+                if parsed.positional.len() == command.positional {
+                    usage_error(Some(command), &format!("Unexpected argument \"{}\"", argument));
+                }
+                parsed.positional.push(argument.clone());
+                continue;
+            }
+        };
+        if !known(name) {
+            usage_error(Some(command), &format!("Unknown option \"{}\"", argument));
+        }
+        let value = match inline_value {
+            Some(value) => String::from(value),
+            None => arguments.next().cloned().unwrap_or_else(|| usage_error(Some(command), &format!("The option --{} needs a value", name)))
+        };
+        parsed.options.push((String::from(name), value));
+    }
+    parsed
+}
+
+fn parse_value<T: std::str::FromStr>(command: &Command, name: &str, value: &str) -> T {
+    value.parse::<T>().unwrap_or_else(|_| usage_error(Some(command), &format!("Invalid {} \"{}\"", name, value)))
+}
+
+/// The settings file, default or given, with the command line's changes applied on top.
+fn load_settings(command: &Command, arguments: &Arguments) -> Settings {
+    let mut text = match arguments.value("settings") {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|error| fail(&format!("Error reading the {} file: {}", path, error))),
+        None => fs::read_to_string("./settings.conf").unwrap_or_default()
+    };
+    if let Err(error) = text.parse::<Settings>() {
+        fail(&error);
+    }
+    for (name, key) in SETTING_OPTIONS.iter() {
+        for value in arguments.values(name) {
+            text.push_str(&format!("\n{}:{}", key, value));
+        }
+    }
+    if let Some(algorithm) = arguments.value("algorithm") {
+        let weave = [false, true].iter().copied().find(|weave| generator::name(*weave) == algorithm).unwrap_or_else(|| {
+            usage_error(Some(command), &format!("Unknown algorithm \"{}\", expected {} or {}", algorithm, generator::name(false), generator::name(true)))
+        });
+        text.push_str(&format!("\nweave:{}", weave));
+    }
+    for line in arguments.values("set") {
+        text.push('\n');
+        text.push_str(line);
+    }
+    text.parse::<Settings>().unwrap_or_else(|error| usage_error(Some(command), &error))
+}
+
+/// The output path and the name of the format to write it in, from `--format` or else the
+/// path's extension. Solutions are written as cell numbers unless the extension is an image.
+fn output(command: &Command, arguments: &Arguments, default: &str) -> (String, String) {
+    let path = arguments.value("output").unwrap_or("-");
+    let format = match (arguments.value("format"), Format::from_path(path)) {
+        (Some(format), _) => format,
+        (None, Some(format)) if command.name != "solve" || format.shows_solution() => format.name(),
+        (None, _) if path == "-" || command.name == "solve" => default,
+        (None, _) => usage_error(Some(command), &format!("Cannot tell the format from \"{}\", choose one with --format", path))
+    };
+    (String::from(path), String::from(format))
+}
+
+fn run_generate(arguments: &Arguments) {
+    let settings = load_settings(&GENERATE, arguments);
+    let (path, format) = output(&GENERATE, arguments, "json");
+    let format = format.parse::<Format>().unwrap_or_else(|error| usage_error(Some(&GENERATE), &error));
+    let seed = arguments.value("seed").map_or_else(rand::random, |seed| parse_value::<u64>(&GENERATE, "seed", seed));
+    if format == Format::Gif && path == "-" {
+        usage_error(Some(&GENERATE), "A GIF needs an output file");
+    }
+
+    // The event log and the GIF both watch the one generation, so the maze is only carved once.
+    let mut generation = Generation::new(&settings, seed).unwrap_or_else(|error| fail(&error));
+    let mut log = settings.event_log.as_ref().map(|log| {
+        EventLog::create(log, generation.maze(), generation.backtracker().start(), generator::name(settings.weave), Some(seed)).unwrap_or_else(|error| fail(&error))
+    });
+    let mut gif = (format == Format::Gif).then(|| GenerationGif::create(&path, &settings, generation.maze(), generation.backtracker()).unwrap_or_else(|error| fail(&error)));
+    while !generation.is_finished() {
+        let step = generation.step();
+        if let Some(log) = log.as_mut() {
+            log.record_step(&step).unwrap_or_else(|error| fail(&error));
+        }
+        if let Some(gif) = gif.as_mut() {
+            gif.record(generation.maze(), generation.backtracker(), &step).unwrap_or_else(|error| fail(&error));
+        }
+    }
+    let maze = generation.finish();
+    if let (Some(log), Some(entrance), Some(exit)) = (log.as_mut(), maze.entrance(), maze.exit()) {
+        log.record(Event::Openings { entrance, exit }).unwrap_or_else(|error| fail(&error));
+    }
+    if let Some(gif) = gif {
+        gif.finish(&maze).unwrap_or_else(|error| fail(&error));
+        return;
+    }
+    let bytes = formats::to_bytes(&maze, format, &Style::from_settings(&settings)).unwrap_or_else(|error| fail(&error));
+    formats::save(&bytes, &path).unwrap_or_else(|error| fail(&error));
+}
+
+fn run_solve(arguments: &Arguments) {
+    let settings = load_settings(&SOLVE, arguments);
+    let (path, format) = output(&SOLVE, arguments, "cells");
+    let format = match format.as_str() {
+        "cells" => None,
+        format => Some(format.parse::<Format>().ok().filter(|format| format.shows_solution() && *format != Format::Gif).unwrap_or_else(|| {
+            usage_error(Some(&SOLVE), &format!("Cannot write a solution as \"{}\", expected cells, terminal, svg or png", format))
+        }))
+    };
+
+    let maze = formats::load(arguments.positional.first().map_or("-", String::as_str)).unwrap_or_else(|error| fail(&error));
+    if maze.entrance().is_none() {
+        fail("The maze has no entrance and exit");
+    }
+    let solution = solver::solve(&maze).unwrap_or_else(|| fail("There is no way from the entrance to the exit"));
+    let bytes = match format {
+        None => solution.iter().map(|cell| format!("{}\n", cell)).collect::<String>().into_bytes(),
+        Some(format) => {
+            let style = Style {
+                solution: true,
+                ..Style::from_settings(&settings)
+            };
+            formats::to_bytes(&maze, format, &style).unwrap_or_else(|error| fail(&error))
+        }
+    };
+    formats::save(&bytes, &path).unwrap_or_else(|error| fail(&error));
+}
+
+fn run_render(arguments: &Arguments) {
+    let settings = load_settings(&RENDER, arguments);
+    let (path, format) = output(&RENDER, arguments, "json");
+    let format = format.parse::<Format>().unwrap_or_else(|error| usage_error(Some(&RENDER), &error));
+    if format == Format::Gif {
+        usage_error(Some(&RENDER), "A GIF animates generating the maze, use `maze_maker generate --format gif` instead");
+    }
+    let maze = formats::load(arguments.positional.first().map_or("-", String::as_str)).unwrap_or_else(|error| fail(&error));
+    let bytes = formats::to_bytes(&maze, format, &Style::from_settings(&settings)).unwrap_or_else(|error| fail(&error));
+    formats::save(&bytes, &path).unwrap_or_else(|error| fail(&error));
+}
+
+fn run_gui(arguments: &Arguments) {
+    let settings = load_settings(&GUI, arguments);
+    let (mut context, event_loop) = ContextBuilder::new("maze_maker", "Amirhosein_GPR").build().unwrap_or_else(|error| fail(&format!("Cannot open a window: {}", error)));
+
+    let game_state = match arguments.value("replay") {
+        Some(path) => Game::replay(&mut context, settings, path).unwrap_or_else(|error| fail(&error)),
+        None => Game::with_settings(&mut context, settings).unwrap_or_else(|error| fail(&error))
+    };
+
+    graphics::set_resizable(&mut context, true).unwrap_or_else(|error| fail(&error.to_string()));
+    graphics::set_drawable_size(&mut context, 720.0, 720.0).unwrap_or_else(|error| fail(&error.to_string()));
+
+    graphics::set_window_title(&context, "Maze maker");
 
     event::run(context, event_loop, game_state);
-}
\ No newline at end of file
+}
+
+fn run_tui(arguments: &Arguments) {
+    let settings = load_settings(&TUI, arguments);
+    let seed = arguments.value("seed").map_or_else(rand::random, |seed| parse_value::<u64>(&TUI, "seed", seed));
+    tui::run(&settings, seed).unwrap_or_else(|error| fail(&error));
+}
+
+fn run_batch(arguments: &Arguments) {
+    let settings = load_settings(&BATCH, arguments);
+    let count = match arguments.positional.first() {
+        Some(count) => parse_value::<usize>(&BATCH, "count", count),
+        None => usage_error(Some(&BATCH), "How many mazes? The count is missing")
+    };
+    let master_seed = arguments.positional.get(1).map_or(0, |seed| parse_value::<u64>(&BATCH, "master seed", seed));
+    let available_threads = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+    let threads = arguments.positional.get(2).map_or(available_threads, |threads| parse_value::<usize>(&BATCH, "thread count", threads));
+
+    if let Some(directory) = arguments.value("output-dir") {
+        write_dataset(arguments, count, master_seed, threads, directory);
+        return;
+    }
+    if arguments.values("size").count() > 1 || arguments.values("algorithm").count() > 1 {
+        usage_error(Some(&BATCH), "Several sizes or algorithms need --output-dir");
+    }
+
+    let started = Instant::now();
+    let mazes = batch::generate_batch(&settings, master_seed, count, threads).unwrap_or_else(|error| fail(&error));
+    for (index, maze) in mazes.iter().enumerate() {
+        println!("{} {} {:016x}", index, batch::maze_seed(master_seed, index as u64), batch::fingerprint(maze));
+    }
+    eprintln!("Generated {} mazes in {:.3} s on {} threads", count, started.elapsed().as_secs_f64(), threads);
+}
+
+/// Writes `count` mazes for every size and algorithm on the command line into `directory`,
+/// followed by the manifest.
+fn write_dataset(arguments: &Arguments, count: usize, master_seed: u64, threads: usize, directory: &str) {
+    let format = arguments.value("format").map_or(Format::Json, |format| format.parse::<Format>().unwrap_or_else(|error| usage_error(Some(&BATCH), &error)));
+    let manifest = arguments.value("manifest").unwrap_or("csv");
+    if manifest != "csv" && manifest != "json" {
+        usage_error(Some(&BATCH), &format!("Unknown manifest format \"{}\", expected csv or json", manifest));
+    }
+    let choices = |name: &str| match arguments.values(name).map(String::from).collect::<Vec<String>>() {
+        values if values.is_empty() => vec![None],
+        values => values.into_iter().map(Some).collect()
+    };
+    let mut variants = Vec::new();
+    for size in choices("size") {
+        for algorithm in choices("algorithm") {
+            let mut options = arguments
+                .options
+                .iter()
+                .filter(|(name, _)| !(size.is_some() && name == "size" || algorithm.is_some() && name == "algorithm"))
+                .cloned()
+                .collect::<Vec<(String, String)>>();
+            options.extend(size.iter().map(|size| (String::from("size"), size.clone())));
+            options.extend(algorithm.iter().map(|algorithm| (String::from("algorithm"), algorithm.clone())));
+            variants.push(load_settings(&BATCH, &Arguments { options, positional: Vec::new() }));
+        }
+    }
+
+    let started = Instant::now();
+    let entries = batch::write_dataset(&variants, count, master_seed, threads, directory, format).unwrap_or_else(|error| fail(&error));
+    let (text, file) = match manifest {
+        "json" => (batch::manifest_json(&entries), "manifest.json"),
+        _ => (batch::manifest_csv(&entries), "manifest.csv")
+    };
+    let path = Path::new(directory).join(file);
+    fs::write(&path, text).unwrap_or_else(|error| fail(&format!("Error writing {}: {}", path.display(), error)));
+    eprintln!("Wrote {} mazes and {} in {:.3} s on {} threads", entries.len(), path.display(), started.elapsed().as_secs_f64(), threads);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maze_maker::maze::Opening;
+    use maze_maker::topology::{Side, TopologyKind, Wrap};
+
+    fn arguments(command: &Command, arguments: &[&str]) -> Arguments {
+        parse_arguments(command, &arguments.iter().map(|argument| String::from(*argument)).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn parses_options_and_positional_arguments() {
+        let parsed = arguments(&BATCH, &["12", "-d", "out", "--size=5x5", "--size", "8x8", "-f", "png", "7"]);
+        assert_eq!(parsed.positional, ["12", "7"]);
+        assert_eq!(parsed.value("output-dir"), Some("out"));
+        assert_eq!(parsed.value("format"), Some("png"));
+        assert_eq!(parsed.values("size").collect::<Vec<&str>>(), ["5x5", "8x8"]);
+        assert_eq!(parsed.value("size"), Some("8x8"));
+        assert_eq!(parsed.value("manifest"), None);
+
+        let parsed = arguments(&SOLVE, &["-", "-o", "-"]);
+        assert_eq!(parsed.positional, ["-"]);
+        assert_eq!(parsed.value("output"), Some("-"));
+    }
+
+    #[test]
+    fn applies_options_over_the_settings_file() {
+        let path = env::temp_dir().join(format!("maze_maker_test_{}.conf", process::id()));
+        fs::write(&path, "table_size:4x4\nwrap:torus\nentrance:farthest\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let settings = load_settings(&GENERATE, &arguments(&GENERATE, &["--settings", path]));
+        assert_eq!((settings.table, settings.wrap, settings.weave), ([4, 4], Wrap::Torus, false));
+
+        let overridden = arguments(&GENERATE, &[
+            "--settings", path,
+            "--size", "6x9",
+            "--wrap", "none",
+            "--topology", "hexagonal",
+            "--algorithm", generator::name(true),
+            "--entrance", "top,2",
+            "--set", "exit:random",
+            "--set", "cell_size:12"
+        ]);
+        let settings = load_settings(&GENERATE, &overridden);
+        assert_eq!((settings.table, settings.wrap, settings.topology, settings.weave), ([6, 9], Wrap::None, TopologyKind::Hexagonal, true));
+        assert_eq!((settings.entrance, settings.exit), (Opening::At(Side::Top, 2), Opening::Random));
+        assert_eq!(settings.cell_size, 12.0);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use crate::topology::{CellId, Direction, Layout, Side, Topology, Vector2D};
+use std::fs;
+use std::path::Path;
+
+/// Which cells of a `rows` by `columns` table take part in the maze. In ASCII masks `X` marks a
+/// cell that is left out and `.` one that is kept; in images black pixels are left out.
+#[derive(Clone, Debug)]
+pub struct Mask {
+    rows: u32,
+    columns: u32,
+    included: Vec<bool>
+}
+
+impl Mask {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        match extension.to_lowercase().as_str() {
+            "png" | "bmp" => {
+                let image = image::open(path).map_err(|error| format!("Error opening the mask image {}: {}", path, error))?.to_luma8();
+                let included = image.pixels().map(|pixel| pixel.0[0] >= 128).collect::<Vec<bool>>();
+                Self::new(image.height() as usize, image.width() as usize, included)
+            },
+            _ => {
+                let text = fs::read_to_string(path).map_err(|error| format!("Error reading the mask file {}: {}", path, error))?;
+                Self::from_ascii(&text)
+            }
+        }
+    }
+
+    pub fn from_ascii(text: &str) -> Result<Self, String> {
+        let rows = text.lines().map(|row| row.trim_end()).filter(|row| !row.is_empty()).collect::<Vec<&str>>();
+        let columns = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let mut included = Vec::new();
+        for row in rows.iter() {
+            for symbol in row.chars().chain(std::iter::repeat('X')).take(columns) {
+                match symbol {
+                    'X' | 'x' => included.push(false),
+                    '.' => included.push(true),
+                    other => return Err(format!("Unexpected character '{}' in mask, expected 'X' or '.'", other))
+                }
+            }
+        }
+        Self::new(rows.len(), columns, included)
+    }
+
+    fn new(rows: usize, columns: usize, included: Vec<bool>) -> Result<Self, String> {
+        if rows == 0 || columns == 0 {
+            return Err(format!("A mask must be at least 1x1 cells but this one is {}x{}", rows, columns));
+        }
+        if !included.contains(&true) {
+            return Err(String::from("A mask must include at least one cell"));
+        }
+        Ok(Self {
+            rows: rows as u32,
+            columns: columns as u32,
+            included
+        })
+    }
+
+    pub fn table(&self) -> [u32; 2] {
+        [self.rows, self.columns]
+    }
+
+    /// The mask as rows of `X` and `.`, the way `from_ascii` reads it.
+    pub fn to_ascii(&self) -> Vec<String> {
+        self.included
+            .chunks(self.columns as usize)
+            .map(|row| row.iter().map(|included| if *included { '.' } else { 'X' }).collect())
+            .collect()
+    }
+}
+
+/// Any row-and-column topology with the cells of a mask cut out. Walls towards masked cells
+/// count as outer walls.
+#[derive(Clone)]
+pub struct Masked {
+    inner: Box<dyn Topology>,
+    mask: Mask
+}
+
+impl Masked {
+    pub fn new(inner: Box<dyn Topology>, mask: Mask) -> Self {
+        Self {
+            inner,
+            mask
+        }
+    }
+}
+
+impl Topology for Masked {
+    fn layout(&self) -> Layout {
+        Layout {
+            mask: Some(self.mask.to_ascii()),
+            ..self.inner.layout()
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.inner.cell_count()
+    }
+
+    fn is_included(&self, cell: CellId) -> bool {
+        self.mask.included[cell]
+    }
+
+    fn direction_count(&self, cell: CellId) -> usize {
+        self.inner.direction_count(cell)
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        self.inner.neighbour(cell, direction).filter(|neighbour| self.mask.included[*neighbour])
+    }
+
+    fn wall_count(&self) -> usize {
+        self.inner.wall_count()
+    }
+
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        self.neighbour(cell, direction)?;
+        self.inner.wall_index(cell, direction)
+    }
+
+    fn opposite(&self, cell: CellId, direction: Direction) -> Direction {
+        self.inner.opposite(cell, direction)
+    }
+
+    fn straight_on(&self, cell: CellId, direction: Direction) -> Option<Direction> {
+        self.inner.straight_on(cell, direction)
+    }
+
+    fn wrap_marker(&self, cell: CellId, direction: Direction) -> Option<Vec<Vector2D>> {
+        self.inner.wrap_marker(cell, direction)
+    }
+
+    /// The outer walls facing `side` that nothing else shadows, so holes inside the shape are
+    /// never picked for openings.
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        let mut lanes: Vec<(i64, f32, (CellId, Direction))> = Vec::new();
+        for cell in (0..self.cell_count()).filter(|cell| self.mask.included[*cell]) {
+            let center = self.inner.cell_center(cell);
+            for direction in (0..self.direction_count(cell)).filter(|direction| self.neighbour(cell, *direction).is_none()) {
+                let wall = self.inner.wall(cell, direction);
+                let middle = Vector2D::new(
+                    wall.iter().map(|point| point.x).sum::<f32>() / wall.len() as f32,
+                    wall.iter().map(|point| point.y).sum::<f32>() / wall.len() as f32
+                );
+                let (outward_x, outward_y) = (middle.x - center.x, middle.y - center.y);
+                let facing = if outward_x.abs() >= outward_y.abs() {
+                    if outward_x > 0.0 { Side::Right } else { Side::Left }
+                } else if outward_y > 0.0 {
+                    Side::Bottom
+                } else {
+                    Side::Top
+                };
+                if facing != side {
+                    continue;
+                }
+                let (along, depth) = match side {
+                    Side::Top => (middle.x, middle.y),
+                    Side::Bottom => (middle.x, -middle.y),
+                    Side::Left => (middle.y, middle.x),
+                    Side::Right => (middle.y, -middle.x)
+                };
+                lanes.push(((along * 1000.0).round() as i64, depth, (cell, direction)));
+            }
+        }
+        lanes.sort_by(|first, second| first.0.cmp(&second.0).then(first.1.partial_cmp(&second.1).unwrap()));
+        lanes.dedup_by_key(|lane| lane.0);
+        lanes.into_iter().map(|lane| lane.2).collect()
+    }
+
+    fn size(&self) -> Vector2D {
+        self.inner.size()
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        self.inner.cell_center(cell)
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        self.inner.cell_polygon(cell)
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        self.inner.wall(cell, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::rectangular::{DOWN, LEFT, RIGHT, UP};
+    use crate::topology::Rectangular;
+    use image::{GrayImage, Luma};
+    use std::env;
+
+    #[test]
+    fn pads_ragged_rows_with_masked_cells() {
+        let mask = Mask::from_ascii("..\n\nx...  \n.\n").unwrap();
+        assert_eq!(mask.table(), [3, 4]);
+        assert_eq!(mask.to_ascii(), ["..XX", "X...", ".XXX"]);
+    }
+
+    #[test]
+    fn rejects_empty_and_unknown_masks() {
+        assert!(Mask::from_ascii("").is_err());
+        assert!(Mask::from_ascii("\n  \n").is_err());
+        assert!(Mask::from_ascii("XX\nxX").is_err());
+        assert!(Mask::from_ascii("X.\n.o").is_err());
+    }
+
+    #[test]
+    fn loads_text_and_images() {
+        let text = env::temp_dir().join(format!("maze_maker_test_mask_{}.txt", std::process::id()));
+        fs::write(&text, ".X.\n...\n").unwrap();
+        let loaded = Mask::load(text.to_str().unwrap());
+        fs::remove_file(&text).unwrap();
+        assert_eq!(loaded.unwrap().to_ascii(), [".X.", "..."]);
+
+        let picture = env::temp_dir().join(format!("maze_maker_test_mask_{}.png", std::process::id()));
+        GrayImage::from_fn(3, 2, |x, y| Luma([if x == 1 && y == 0 { 0 } else { 255 }])).save(&picture).unwrap();
+        let loaded = Mask::load(picture.to_str().unwrap());
+        fs::remove_file(&picture).unwrap();
+        assert_eq!(loaded.unwrap().to_ascii(), [".X.", "..."]);
+
+        let black = env::temp_dir().join(format!("maze_maker_test_mask_black_{}.png", std::process::id()));
+        GrayImage::new(2, 2).save(&black).unwrap();
+        let loaded = Mask::load(black.to_str().unwrap());
+        fs::remove_file(&black).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn borders_skip_shadowed_walls() {
+        let masked = |text: &str| {
+            let mask = Mask::from_ascii(text).unwrap();
+            let [rows, columns] = mask.table();
+            Masked::new(Box::new(Rectangular::new(rows, columns)), mask)
+        };
+        let notched = masked(".X.\n...");
+        assert_eq!(notched.border(Side::Top), [(0, UP), (4, UP), (2, UP)]);
+        assert_eq!(notched.border(Side::Bottom), [(3, DOWN), (4, DOWN), (5, DOWN)]);
+
+        let holed = masked("...\n.X.\n...");
+        assert_eq!(holed.border(Side::Top), [(0, UP), (1, UP), (2, UP)]);
+        assert_eq!(holed.border(Side::Bottom), [(6, DOWN), (7, DOWN), (8, DOWN)]);
+        assert_eq!(holed.border(Side::Left), [(0, LEFT), (3, LEFT), (6, LEFT)]);
+        assert_eq!(holed.border(Side::Right), [(2, RIGHT), (5, RIGHT), (8, RIGHT)]);
+    }
+}
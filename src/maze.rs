@@ -0,0 +1,345 @@
+use crate::bitset::BitSet;
+use crate::topology::{CellId, Direction, Side, Topology};
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+/// Where an opening in the outer wall goes: a fixed border cell on one side, a random border
+/// cell, or the border cell farthest (by path length) from the other opening.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opening {
+    At(Side, usize),
+    Random,
+    FarthestApart
+}
+
+impl FromStr for Opening {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        match text {
+            "random" => return Ok(Opening::Random),
+            "farthest" => return Ok(Opening::FarthestApart),
+            _ => ()
+        }
+        let parts = text.split(',').collect::<Vec<&str>>();
+        if parts.len() != 2 {
+            return Err(format!("Expected \"random\", \"farthest\" or \"<side>,<index>\" but found \"{}\"", text));
+        }
+        let side = parts[0].parse::<Side>()?;
+        let index = parts[1].trim().parse::<usize>().map_err(|error| format!("Invalid index \"{}\": {}", parts[1], error))?;
+        Ok(Opening::At(side, index))
+    }
+}
+
+impl Opening {
+    /// Fails for an index past the end of its side. A side with no border at all, such as the
+    /// left of a grid wrapped into a cylinder, is fine: the opening goes to a random border cell.
+    pub fn check(&self, topology: &dyn Topology) -> Result<(), String> {
+        if let Opening::At(side, index) = self {
+            let length = topology.border(*side).len();
+            if length > 0 && *index >= length {
+                return Err(format!("Opening index {} is out of range for the {:?} side, which has {} cells", index, side, length));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a maze came from, kept so saved mazes can be traced back and regenerated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Provenance {
+    pub generator: String,
+    pub version: String,
+    pub seed: Option<u64>
+}
+
+/// A maze on some topology: which walls have been carved away, which cells a passage tunnels
+/// under, plus the entrance and exit once they have been cut into the outer wall. Carved walls
+/// are kept one bit each, in the slots the topology hands out.
+pub struct Maze {
+    topology: Box<dyn Topology>,
+    passages: BitSet,
+    crossings: HashMap<CellId, Direction>,
+    entrance: Option<(CellId, Direction)>,
+    exit: Option<(CellId, Direction)>,
+    provenance: Option<Provenance>
+}
+
+impl Maze {
+    pub fn new(topology: Box<dyn Topology>) -> Self {
+        let passages = BitSet::new(topology.wall_count());
+        Self {
+            topology,
+            passages,
+            crossings: HashMap::new(),
+            entrance: None,
+            exit: None,
+            provenance: None
+        }
+    }
+
+    pub fn topology(&self) -> &dyn Topology {
+        self.topology.as_ref()
+    }
+
+    pub fn entrance(&self) -> Option<(CellId, Direction)> {
+        self.entrance
+    }
+
+    pub fn exit(&self) -> Option<(CellId, Direction)> {
+        self.exit
+    }
+
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    pub fn set_provenance(&mut self, provenance: Provenance) {
+        self.provenance = Some(provenance);
+    }
+
+    /// The carved walls, one bit per slot of `Topology::wall_index`.
+    pub fn passages(&self) -> &BitSet {
+        &self.passages
+    }
+
+    /// Replaces every carved wall at once, for loading saved mazes.
+    pub fn set_passages(&mut self, passages: BitSet) -> Result<(), String> {
+        if passages.len() != self.topology.wall_count() {
+            return Err(format!("The topology has {} wall slots but {} were given", self.topology.wall_count(), passages.len()));
+        }
+        self.passages = passages;
+        Ok(())
+    }
+
+    /// Outer walls are only ever open where the entrance and exit are.
+    pub fn is_open(&self, cell: CellId, direction: Direction) -> bool {
+        match self.topology.wall_index(cell, direction) {
+            Some(index) => self.passages.contains(index),
+            None => self.entrance == Some((cell, direction)) || self.exit == Some((cell, direction))
+        }
+    }
+
+    pub fn has_wall(&self, cell: CellId, direction: Direction) -> bool {
+        !self.is_open(cell, direction)
+    }
+
+    /// Removes the wall between `cell` and its neighbour in `direction`. Outer walls are opened
+    /// by `make_openings` instead.
+    pub fn carve(&mut self, cell: CellId, direction: Direction) {
+        if let Some(index) = self.topology.wall_index(cell, direction) {
+            self.passages.insert(index);
+        }
+    }
+
+    /// Carves a passage from `cell` under its neighbour in `direction` and out the far side of
+    /// it, leaving the neighbour's own passage on top. Returns the cell the tunnel comes out in.
+    pub fn tunnel(&mut self, cell: CellId, direction: Direction) -> CellId {
+        let under = self.topology.neighbour(cell, direction).expect("Cannot tunnel through the outer wall");
+        let exit = self.topology.straight_on(under, self.topology.opposite(cell, direction)).expect("This topology does not support weaving");
+        self.carve(cell, direction);
+        self.carve(under, exit);
+        self.set_crossing(under, exit);
+        self.topology.neighbour(under, exit).unwrap()
+    }
+
+    /// For a cell that a tunnel runs under, the lower of the two sides the tunnel passes through.
+    pub fn crossing(&self, cell: CellId) -> Option<Direction> {
+        self.crossings.get(&cell).copied()
+    }
+
+    /// Records that a tunnel runs under `cell` through `under` and the side across from it.
+    /// The walls on its way must be carved separately.
+    pub fn set_crossing(&mut self, cell: CellId, under: Direction) {
+        let across = self.topology.straight_on(cell, under).expect("This topology does not support weaving");
+        self.crossings.insert(cell, under.min(across));
+    }
+
+    fn is_under(&self, cell: CellId, direction: Direction) -> bool {
+        self.crossing(cell).is_some_and(|under| under == direction || self.topology.straight_on(cell, under) == Some(direction))
+    }
+
+    /// The cells reachable in one move. A crossing cell holds two separate passages: its own
+    /// links only follow the passage on top, and moving into it along the tunnel comes out on
+    /// the far side.
+    pub fn links(&self, cell: CellId) -> Vec<CellId> {
+        (0..self.topology.direction_count(cell)).filter_map(|direction| self.link(cell, direction)).collect()
+    }
+
+    /// The cell one move from `cell` through its side `direction`, as `links` finds it.
+    pub fn link(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        if !self.is_open(cell, direction) || self.is_under(cell, direction) {
+            return None;
+        }
+        let neighbour = self.topology.neighbour(cell, direction)?;
+        let entry = self.topology.opposite(cell, direction);
+        if self.is_under(neighbour, entry) {
+            return self.topology.neighbour(neighbour, self.topology.straight_on(neighbour, entry)?);
+        }
+        Some(neighbour)
+    }
+
+    /// Path lengths from `start` to every cell, `u32::MAX` for cells it cannot reach.
+    pub fn distances_from(&self, start: CellId) -> Vec<u32> {
+        let mut distances = vec![u32::MAX; self.topology.cell_count()];
+        distances[start] = 0;
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(cell) = queue.pop_front() {
+            for next in self.links(cell) {
+                if distances[next] == u32::MAX {
+                    distances[next] = distances[cell] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    fn border_walls(&self) -> Vec<(CellId, Direction)> {
+        Side::ALL.iter().flat_map(|side| self.topology.border(*side)).collect()
+    }
+
+    fn random_wall<R: Rng>(&self, other: Option<(CellId, Direction)>, rng: &mut R) -> (CellId, Direction) {
+        let walls = self.border_walls();
+        let candidates = walls.iter()
+            .filter(|wall| other.is_none_or(|other| other.0 != wall.0))
+            .copied()
+            .collect::<Vec<(CellId, Direction)>>();
+        let candidates = if candidates.is_empty() { walls } else { candidates };
+        candidates[rng.gen_range(0..candidates.len())]
+    }
+
+    fn farthest_wall(&self, from: (CellId, Direction)) -> (CellId, Direction) {
+        let distances = self.distances_from(from.0);
+        self.border_walls()
+            .into_iter()
+            .filter(|wall| wall.0 != from.0 && distances[wall.0] != u32::MAX)
+            .max_by_key(|wall| distances[wall.0])
+            .unwrap_or(from)
+    }
+
+    /// Picks the entrance and exit walls and carves them out of the outer wall. Openings that
+    /// depend on path length should only be resolved once the maze is finished. An opening on a
+    /// side that wraps round, and so has no outer wall, goes to a random border cell instead.
+    /// Mazes without an outer wall at all, such as a torus, get none.
+    pub fn make_openings<R: Rng>(&mut self, entrance: Opening, exit: Opening, rng: &mut R) -> Option<((CellId, Direction), (CellId, Direction))> {
+        if self.border_walls().is_empty() {
+            return None;
+        }
+        let fixed = |maze: &Self, opening: Opening, other: Option<(CellId, Direction)>, rng: &mut R| match opening {
+            Opening::At(side, index) if !maze.topology.border(side).is_empty() => Some(maze.topology.border(side)[index]),
+            Opening::At(..) | Opening::Random => Some(maze.random_wall(other, rng)),
+            Opening::FarthestApart => None
+        };
+        let fixed_entrance = fixed(self, entrance, None, rng);
+        let fixed_exit = fixed(self, exit, fixed_entrance, rng);
+        let (entrance, exit) = match (fixed_entrance, fixed_exit) {
+            (Some(entrance), Some(exit)) => (entrance, exit),
+            (Some(entrance), None) => (entrance, self.farthest_wall(entrance)),
+            (None, Some(exit)) => (self.farthest_wall(exit), exit),
+            (None, None) => {
+                let entrance = self.farthest_wall(self.border_walls()[0]);
+                (entrance, self.farthest_wall(entrance))
+            }
+        };
+        self.set_openings(entrance, exit);
+        Some((entrance, exit))
+    }
+
+    /// Opens the given outer walls as the entrance and exit.
+    pub fn set_openings(&mut self, entrance: (CellId, Direction), exit: (CellId, Direction)) {
+        self.entrance = Some(entrance);
+        self.exit = Some(exit);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::batch;
+    use crate::generator::{self, Backtracker};
+    use crate::topology::rectangular::{Rectangular, Wrap};
+    use crate::topology::{Layout, TopologyKind};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Generates a maze from settings written the way `settings.conf` is.
+    pub(crate) fn generated(settings: &str, seed: u64) -> Maze {
+        let settings = settings.parse().unwrap_or_else(|error| panic!("{}: {}", settings, error));
+        batch::generate(&settings, seed).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Generates a maze on a layout directly, for topologies settings can only reach through
+    /// files, such as masks.
+    pub(crate) fn generated_on(layout: Layout, seed: u64) -> Maze {
+        let mut maze = Maze::new(layout.build().unwrap_or_else(|error| panic!("{}", error)));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let start = generator::random_start(maze.topology(), &mut rng);
+        Backtracker::new(&maze, start, false).run(&mut maze, &mut rng);
+        maze.make_openings(Opening::Random, Opening::FarthestApart, &mut rng);
+        maze
+    }
+
+    /// A finished maze on every kind of topology, named for failure messages.
+    pub(crate) fn samples() -> Vec<(String, Maze)> {
+        let mut samples = [
+            "table_size:6x7",
+            "table_size:1x1",
+            "topology:hexagonal\ntable_size:5x6",
+            "topology:triangular\ntable_size:5x8",
+            "topology:polar\ntable_size:5x5",
+            "wrap:cylinder\ntable_size:6x6",
+            "wrap:torus\ntable_size:6x6",
+            "wrap:mobius\ntable_size:6x6",
+            "wrap:klein\ntable_size:6x6",
+            "weave:true\ntable_size:10x10",
+            "floors:3\ntable_size:4x4",
+            "floors:2\nfloor_view:single\ntopology:hexagonal\ntable_size:4x5",
+            "floors:2\nwrap:mobius\ntable_size:4x4",
+            "floors:2\ntopology:polar\ntable_size:4x4"
+        ]
+        .iter()
+        .map(|settings| (settings.replace('\n', " "), generated(settings, 7)))
+        .collect::<Vec<(String, Maze)>>();
+        let mask = Some(["XX..XX", "......", "..XX..", "......", "X....X"].iter().map(|row| String::from(*row)).collect::<Vec<String>>());
+        for kind in [TopologyKind::Rectangular, TopologyKind::Hexagonal, TopologyKind::Triangular] {
+            let layout = Layout {
+                mask: mask.clone(),
+                ..Layout::new(kind, 5, 6)
+            };
+            samples.push((format!("masked {}", kind), generated_on(layout, 7)));
+        }
+        samples
+    }
+
+    /// Fails unless both mazes have the same topology, walls, crossings and openings.
+    pub(crate) fn assert_same_maze(maze: &Maze, other: &Maze) {
+        assert_eq!(maze.topology().layout(), other.topology().layout());
+        assert_eq!(maze.passages(), other.passages());
+        for cell in 0..maze.topology().cell_count() {
+            assert_eq!(maze.crossing(cell), other.crossing(cell), "crossing at cell {}", cell);
+        }
+        assert_eq!((maze.entrance(), maze.exit()), (other.entrance(), other.exit()));
+    }
+
+    #[test]
+    fn openings_on_a_wrapped_side_fall_back_to_a_random_border_cell() {
+        let mut maze = Maze::new(Box::new(Rectangular::with_wrap(4, 5, Wrap::Cylinder)));
+        let entrance = Opening::At(Side::Left, 0);
+        assert!(entrance.check(maze.topology()).is_ok());
+        let (entrance, exit) = maze.make_openings(entrance, Opening::Random, &mut StdRng::seed_from_u64(0)).unwrap();
+        for (cell, direction) in [entrance, exit] {
+            assert!(maze.topology().neighbour(cell, direction).is_none());
+        }
+        assert!(Opening::At(Side::Top, 5).check(maze.topology()).is_err());
+    }
+
+    #[test]
+    fn a_torus_gets_no_openings() {
+        let mut maze = Maze::new(Box::new(Rectangular::with_wrap(4, 5, Wrap::Torus)));
+        assert_eq!(maze.make_openings(Opening::At(Side::Left, 0), Opening::FarthestApart, &mut StdRng::seed_from_u64(0)), None);
+    }
+}
@@ -0,0 +1,371 @@
+mod gif;
+mod png;
+mod svg;
+
+pub use gif::{save_generation_gif, GenerationGif};
+pub use png::{save_png, to_image};
+pub use svg::to_svg;
+
+use crate::bitset::BitSet;
+use crate::generator::Backtracker;
+use crate::maze::Maze;
+use crate::settings::Settings;
+use crate::solver;
+use crate::topology::{self, CellId, Direction, Stairs, Vector2D};
+use std::collections::HashSet;
+
+const WRAP_MARKER_COLOR: [f32; 4] = [0.0, 0.3, 0.8, 1.0];
+const SOLUTION_COLOR: [f32; 4] = [0.1, 0.3, 0.9, 1.0];
+const HEAT_COLOR: [f32; 4] = [0.85, 0.1, 0.1, 1.0];
+const ENTRANCE_COLOR: [f32; 4] = [0.1, 0.7, 0.2, 1.0];
+const EXIT_COLOR: [f32; 4] = [0.85, 0.1, 0.1, 1.0];
+const HEAD_COLOR: [f32; 4] = [0.5, 0.0, 0.0, 1.0];
+
+/// How exported pictures look. Sizes are in pixels; every layout unit is `cell_size` pixels.
+#[derive(Clone, Debug)]
+pub struct Style {
+    pub cell_size: f32,
+    pub margin: f32,
+    pub wall_thickness: f32,
+    pub background_color: [f32; 4],
+    pub foreground_color: [f32; 4],
+    pub wall_color: [f32; 4],
+    pub solution: bool,
+    pub openings: bool,
+    pub heatmap: bool
+}
+
+impl Style {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            cell_size: settings.cell_size,
+            margin: settings.margin,
+            wall_thickness: settings.wall_thickness,
+            background_color: settings.background_color,
+            foreground_color: settings.foreground_color,
+            wall_color: [0.0, 0.0, 0.0, 1.0],
+            solution: settings.show_solution,
+            openings: settings.show_openings,
+            heatmap: settings.show_heatmap
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::from_settings(&Settings::default())
+    }
+}
+
+/// Something to draw, in pixels.
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Polygon { points: Vec<Vector2D>, color: [f32; 4] },
+    Line { points: Vec<Vector2D>, width: f32, color: [f32; 4] },
+    Circle { center: Vector2D, radius: f32, color: [f32; 4] }
+}
+
+/// Shapes that belong together, drawn in order. Vector formats keep layers apart so they can
+/// be hidden one by one.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub name: &'static str,
+    pub shapes: Vec<Shape>
+}
+
+/// A finished maze laid out for drawing: the picture size and its layers, bottom first.
+#[derive(Clone, Debug)]
+pub struct Scene {
+    pub size: Vector2D,
+    pub layers: Vec<Layer>
+}
+
+struct Painter<'a> {
+    maze: &'a Maze,
+    style: &'a Style,
+    /// The generator while the maze is still being carved, so cells it has not reached yet
+    /// keep the background colour.
+    backtracker: Option<&'a Backtracker>,
+    /// The cells the window has lit so far, when it draws its own.
+    lit: Option<&'a BitSet>,
+    /// The cells to draw, when only part of the picture is redrawn.
+    only: Option<&'a HashSet<CellId>>
+}
+
+impl Painter<'_> {
+    fn point(&self, point: Vector2D) -> Vector2D {
+        Vector2D::new(self.style.margin + point.x * self.style.cell_size, self.style.margin + point.y * self.style.cell_size)
+    }
+
+    fn points(&self, points: &[Vector2D]) -> Vec<Vector2D> {
+        points.iter().map(|point| self.point(*point)).collect()
+    }
+
+    fn size(&self) -> Vector2D {
+        let size = self.maze.topology().size();
+        Vector2D::new(size.x * self.style.cell_size + self.style.margin * 2.0, size.y * self.style.cell_size + self.style.margin * 2.0)
+    }
+
+    fn background(&self) -> Layer {
+        let size = self.size();
+        let background = Shape::Polygon {
+            points: vec![Vector2D::new(0.0, 0.0), Vector2D::new(size.x, 0.0), size, Vector2D::new(0.0, size.y)],
+            color: self.style.background_color
+        };
+        Layer { name: "background", shapes: vec![background] }
+    }
+
+    fn included_cells(&self) -> Vec<CellId> {
+        let topology = self.maze.topology();
+        match self.only {
+            Some(only) => {
+                let mut cells = only.iter().copied().filter(|cell| topology.is_included(*cell)).collect::<Vec<CellId>>();
+                cells.sort_unstable();
+                cells
+            },
+            None => (0..topology.cell_count()).filter(|cell| topology.is_included(*cell)).collect()
+        }
+    }
+
+    /// Left, top, right and bottom of everything drawn for a cell, its walls and wrap markers
+    /// included.
+    fn bounds(&self, cell: CellId) -> [f32; 4] {
+        let topology = self.maze.topology();
+        let reach = self.style.wall_thickness / 2.0 + 1.0;
+        topology.cell_polygon(cell).into_iter()
+            .chain((0..topology.direction_count(cell)).flat_map(|direction| {
+                topology.wall(cell, direction).into_iter().chain(topology.wrap_marker(cell, direction).into_iter().flatten())
+            }))
+            .map(|point| self.point(point))
+            .fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |[left, top, right, bottom], point| {
+                [left.min(point.x - reach), top.min(point.y - reach), right.max(point.x + reach), bottom.max(point.y + reach)]
+            })
+    }
+
+    fn wall_middle(&self, (cell, direction): (CellId, Direction)) -> Vector2D {
+        let wall = self.maze.topology().wall(cell, direction);
+        if wall.is_empty() {
+            return self.maze.topology().cell_center(cell);
+        }
+        Vector2D::new(
+            wall.iter().map(|point| point.x).sum::<f32>() / wall.len() as f32,
+            wall.iter().map(|point| point.y).sum::<f32>() / wall.len() as f32
+        )
+    }
+
+    fn cells(&self) -> Layer {
+        let shapes = self.included_cells().into_iter()
+            .map(|cell| {
+                let visited = self.backtracker.is_none_or(|backtracker| backtracker.is_visited(cell)) && self.lit.is_none_or(|lit| lit.contains(cell));
+                let color = if visited { self.style.foreground_color } else { self.style.background_color };
+                Shape::Polygon { points: self.points(&self.maze.topology().cell_polygon(cell)), color }
+            })
+            .collect();
+        Layer { name: "cells", shapes }
+    }
+
+    /// The cell the generator is carving from, as the window shows it.
+    fn head(&self) -> Layer {
+        let shapes = self.backtracker.and_then(Backtracker::head)
+            .map(|head| Shape::Circle { center: self.point(self.maze.topology().cell_center(head)), radius: self.style.cell_size * 0.3, color: HEAD_COLOR })
+            .into_iter()
+            .collect();
+        Layer { name: "head", shapes }
+    }
+
+    /// Cells shaded from the foreground colour near the entrance to red at the farthest point.
+    fn heatmap(&self) -> Layer {
+        let included_cells = self.included_cells();
+        let start = self.maze.entrance().map_or(included_cells[0], |entrance| entrance.0);
+        let distances = self.maze.distances_from(start);
+        let farthest = distances.iter().filter(|distance| **distance != u32::MAX).max().copied().unwrap_or(0).max(1);
+        let shapes = included_cells.into_iter()
+            .filter(|cell| distances[*cell] != u32::MAX)
+            .map(|cell| {
+                let heat = distances[cell] as f32 / farthest as f32;
+                let mut color = self.style.foreground_color;
+                for (channel, hot) in color.iter_mut().zip(HEAT_COLOR) {
+                    *channel += (hot - *channel) * heat;
+                }
+                Shape::Polygon { points: self.points(&self.maze.topology().cell_polygon(cell)), color }
+            })
+            .collect();
+        Layer { name: "heatmap", shapes }
+    }
+
+    fn markers(&self) -> Layer {
+        let topology = self.maze.topology();
+        let mut shapes = Vec::new();
+        for cell in self.included_cells() {
+            for direction in 0..topology.direction_count(cell) {
+                if let Some(marker) = topology.wrap_marker(cell, direction) {
+                    shapes.push(Shape::Polygon { points: self.points(&marker), color: WRAP_MARKER_COLOR });
+                }
+                if let Some(stairs) = topology.stairs(cell, direction).filter(|_| self.maze.is_open(cell, direction)) {
+                    let center = topology.cell_center(cell);
+                    let (shift, tip, base) = match stairs {
+                        Stairs::Up => (-0.2, -0.2, 0.15),
+                        Stairs::Down => (0.2, 0.2, -0.15)
+                    };
+                    let points = self.points(&[
+                        Vector2D::new(center.x + shift, center.y + tip),
+                        Vector2D::new(center.x + shift + 0.15, center.y + base),
+                        Vector2D::new(center.x + shift - 0.15, center.y + base)
+                    ]);
+                    shapes.push(Shape::Polygon { points, color: self.style.wall_color });
+                }
+            }
+        }
+        Layer { name: "markers", shapes }
+    }
+
+    /// Every wall still standing, drawn once from the side that owns it.
+    fn walls(&self) -> Layer {
+        let topology = self.maze.topology();
+        let mut shapes = Vec::new();
+        for cell in self.included_cells() {
+            for direction in 0..topology.direction_count(cell) {
+                let owned = topology.wrap_marker(cell, direction).is_some() || topology.neighbour(cell, direction).is_none_or(|neighbour| cell < neighbour);
+                let wall = topology.wall(cell, direction);
+                if owned && !wall.is_empty() && self.maze.has_wall(cell, direction) {
+                    shapes.push(Shape::Line { points: self.points(&wall), width: self.style.wall_thickness, color: self.style.wall_color });
+                }
+            }
+        }
+        Layer { name: "walls", shapes }
+    }
+
+    /// The passage on top of each crossing, with the tunnel showing through on either side.
+    fn crossings(&self) -> Layer {
+        let mut shapes = Vec::new();
+        for cell in self.included_cells() {
+            if let Some(under) = self.maze.crossing(cell) {
+                let [under_band, over_band] = topology::crossing_bands(self.maze.topology(), cell, under);
+                let foreground = self.style.foreground_color;
+                let shade = [foreground[0] * 0.7, foreground[1] * 0.7, foreground[2] * 0.7, foreground[3]];
+                let over_band = self.points(&over_band);
+                shapes.push(Shape::Polygon { points: self.points(&under_band), color: shade });
+                shapes.push(Shape::Polygon { points: over_band.clone(), color: foreground });
+                let width = self.style.wall_thickness * 0.6;
+                shapes.push(Shape::Line { points: over_band[1..3].to_vec(), width, color: self.style.wall_color });
+                shapes.push(Shape::Line { points: vec![over_band[3], over_band[0]], width, color: self.style.wall_color });
+            }
+        }
+        Layer { name: "crossings", shapes }
+    }
+
+    /// The way from the entrance to the exit through the cell centres, broken wherever it
+    /// takes stairs or wraps around an edge.
+    fn solution(&self) -> Layer {
+        let topology = self.maze.topology();
+        let mut shapes = Vec::new();
+        if let (Some(path), Some(entrance), Some(exit)) = (solver::solve(self.maze), self.maze.entrance(), self.maze.exit()) {
+            let width = (self.style.cell_size * 0.2).max(1.0);
+            let mut points = vec![self.point(self.wall_middle(entrance))];
+            for pair in path.windows(2) {
+                points.push(self.point(topology.cell_center(pair[0])));
+                let jumps = (0..topology.direction_count(pair[0]))
+                    .filter(|direction| topology.neighbour(pair[0], *direction) == Some(pair[1]))
+                    .any(|direction| topology.stairs(pair[0], direction).is_some() || topology.wrap_marker(pair[0], direction).is_some());
+                if jumps {
+                    shapes.push(Shape::Line { points, width, color: SOLUTION_COLOR });
+                    points = Vec::new();
+                }
+            }
+            points.push(self.point(topology.cell_center(*path.last().unwrap())));
+            points.push(self.point(self.wall_middle(exit)));
+            shapes.push(Shape::Line { points, width, color: SOLUTION_COLOR });
+        }
+        Layer { name: "solution", shapes }
+    }
+
+    fn openings(&self) -> Layer {
+        let radius = self.style.cell_size * 0.25;
+        let shapes = [(self.maze.entrance(), ENTRANCE_COLOR), (self.maze.exit(), EXIT_COLOR)].iter()
+            .filter_map(|(opening, color)| opening.map(|opening| Shape::Circle { center: self.point(self.wall_middle(opening)), radius, color: *color }))
+            .collect();
+        Layer { name: "openings", shapes }
+    }
+
+    fn generation_scene(&self) -> Scene {
+        Scene {
+            size: self.size(),
+            layers: vec![self.background(), self.cells(), self.markers(), self.walls(), self.crossings(), self.head()]
+        }
+    }
+}
+
+/// Lays out a maze for exporting, with the optional layers the style asks for.
+pub fn scene(maze: &Maze, style: &Style) -> Scene {
+    let painter = Painter { maze, style, backtracker: None, lit: None, only: None };
+    let size = painter.size();
+    let mut layers = vec![painter.background(), painter.cells()];
+    if style.heatmap {
+        layers.push(painter.heatmap());
+    }
+    layers.push(painter.markers());
+    layers.push(painter.walls());
+    layers.push(painter.crossings());
+    if style.solution {
+        layers.push(painter.solution());
+    }
+    if style.openings {
+        layers.push(painter.openings());
+    }
+    Scene { size, layers }
+}
+
+/// Lays out a maze part way through generation: cells the generator has not reached yet in
+/// the background colour and the cell it is carving from marked. There is no solution,
+/// heatmap or openings until the maze is finished.
+pub fn generation_scene(maze: &Maze, style: &Style, backtracker: &Backtracker) -> Scene {
+    Painter { maze, style, backtracker: Some(backtracker), lit: None, only: None }.generation_scene()
+}
+
+/// What the window draws of `cells`: their fills, in the foreground colour once `lit`, then
+/// their markers, walls and crossings. With a `cell_size` of 1 and no margin the shapes are in
+/// layout units, ready for the window to place.
+pub fn window_scene(maze: &Maze, style: &Style, lit: &BitSet, cells: &HashSet<CellId>) -> Scene {
+    let painter = Painter { maze, style, backtracker: None, lit: Some(lit), only: Some(cells) };
+    Scene {
+        size: painter.size(),
+        layers: vec![painter.cells(), painter.markers(), painter.walls(), painter.crossings()]
+    }
+}
+
+/// Only the shapes of `cells` from `generation_scene`, in the same order, for redrawing the
+/// part of a picture they cover.
+fn partial_generation_scene(maze: &Maze, style: &Style, backtracker: &Backtracker, cells: &HashSet<CellId>) -> Scene {
+    Painter { maze, style, backtracker: Some(backtracker), lit: None, only: Some(cells) }.generation_scene()
+}
+
+/// The pixel bounds of every included cell as `Painter::bounds` gives them, `None` for the
+/// cells a mask leaves out.
+fn cell_bounds(maze: &Maze, style: &Style) -> Vec<Option<[f32; 4]>> {
+    let painter = Painter { maze, style, backtracker: None, lit: None, only: None };
+    (0..maze.topology().cell_count()).map(|cell| maze.topology().is_included(cell).then(|| painter.bounds(cell))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::samples;
+
+    #[test]
+    fn the_window_draws_what_is_exported() {
+        let style = Style { solution: false, openings: false, heatmap: false, ..Style::default() };
+        for (name, maze) in samples() {
+            let cells = (0..maze.topology().cell_count()).collect::<HashSet<CellId>>();
+            let mut lit = BitSet::new(maze.topology().cell_count());
+            for cell in &cells {
+                lit.insert(*cell);
+            }
+            let exported = scene(&maze, &style);
+            let drawn = window_scene(&maze, &style, &lit, &cells);
+            assert_eq!(format!("{:?}", drawn.layers), format!("{:?}", &exported.layers[1..]), "{}", name);
+
+            let unlit = window_scene(&maze, &style, &BitSet::new(maze.topology().cell_count()), &cells);
+            assert!(unlit.layers[0].shapes.iter().all(|shape| matches!(shape, Shape::Polygon { color, .. } if *color == style.background_color)), "{}", name);
+        }
+    }
+}
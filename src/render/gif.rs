@@ -0,0 +1,315 @@
+use super::png::{rasterize, rasterize_area};
+use super::{cell_bounds, generation_scene, partial_generation_scene, scene, Style};
+use crate::batch::Generation;
+use crate::generator::{Backtracker, Step};
+use crate::maze::Maze;
+use crate::settings::Settings;
+use crate::topology::CellId;
+use gif::{Encoder, Frame, Repeat};
+use image::{GenericImage, RgbaImage};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Frames of a GIF being written. Each frame only stores the part of the picture that changed
+/// since the one before, and a picture that did not change at all just holds the previous
+/// frame longer, so long generations stay small.
+struct Animation<W: Write> {
+    encoder: Encoder<W>,
+    shown: Option<RgbaImage>,
+    pending: Option<Frame<'static>>
+}
+
+/// A frame with its own palette of the most common colours in the picture. Mazes are flat
+/// colours with soft edges, so every colour that covers any area makes the palette and the
+/// rare in-between shades of the edges take the nearest one. A general quantizer would rather
+/// merge small marks, like the openings, into what surrounds them.
+fn frame(picture: &RgbaImage) -> Frame<'static> {
+    let mut counts = HashMap::<[u8; 3], usize>::new();
+    for pixel in picture.pixels() {
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+    }
+    let mut colors = counts.into_iter().collect::<Vec<([u8; 3], usize)>>();
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    colors.truncate(256);
+    let palette = colors.iter().map(|(color, _)| *color).collect::<Vec<[u8; 3]>>();
+
+    let mut indices = HashMap::<[u8; 3], u8>::new();
+    let pixels = picture.pixels()
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2]];
+            *indices.entry(color).or_insert_with(|| {
+                let distance = |other: &[u8; 3]| (0..3).map(|channel| (color[channel] as i32 - other[channel] as i32).pow(2)).sum::<i32>();
+                (0..palette.len()).min_by_key(|index| distance(&palette[*index])).unwrap() as u8
+            })
+        })
+        .collect::<Vec<u8>>();
+    Frame::from_palette_pixels(picture.width() as u16, picture.height() as u16, &pixels, &palette.concat(), None)
+}
+
+/// The smallest rectangle holding every pixel inside `area` that differs, as left, top, width
+/// and height.
+fn changed_area(before: &RgbaImage, after: &RgbaImage, area: [u32; 4]) -> Option<(u32, u32, u32, u32)> {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for y in area[1]..area[3] {
+        for x in area[0]..area[2] {
+            if before.get_pixel(x, y) != after.get_pixel(x, y) {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x + 1);
+                bottom = bottom.max(y + 1);
+            }
+        }
+    }
+    (left < right).then(|| (left, top, right - left, bottom - top))
+}
+
+impl<W: Write> Animation<W> {
+    fn new(writer: W, width: u32, height: u32) -> Result<Self, String> {
+        let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => return Err(format!("A GIF can be at most 65535 pixels across but the maze is {}x{}", width, height))
+        };
+        let mut encoder = Encoder::new(writer, width, height, &[]).map_err(|error| format!("Error starting the GIF: {}", error))?;
+        encoder.set_repeat(Repeat::Infinite).map_err(|error| format!("Error starting the GIF: {}", error))?;
+        Ok(Self {
+            encoder,
+            shown: None,
+            pending: None
+        })
+    }
+
+    /// Adds the picture as the next frame. Only the pixels inside `area` (left, top, right and
+    /// bottom) may differ from the frame before, which keeps comparing them cheap.
+    fn push(&mut self, picture: &RgbaImage, area: [u32; 4], delay_in_miliseconds: u32) -> Result<(), String> {
+        // GIF delays are in hundredths of a second.
+        let delay = u16::try_from(delay_in_miliseconds / 10).unwrap_or(u16::MAX);
+        let changed = match &self.shown {
+            Some(shown) => changed_area(shown, picture, area),
+            None => Some((0, 0, picture.width(), picture.height()))
+        };
+        match (changed, &mut self.pending) {
+            (None, Some(pending)) => pending.delay = pending.delay.saturating_add(delay),
+            (None, None) => (),
+            (Some((left, top, width, height)), _) => {
+                self.flush()?;
+                let changed = RgbaImage::from_fn(width, height, |x, y| *picture.get_pixel(left + x, top + y));
+                let mut frame = frame(&changed);
+                frame.left = left as u16;
+                frame.top = top as u16;
+                frame.delay = delay;
+                self.pending = Some(frame);
+                match &mut self.shown {
+                    Some(shown) => shown.copy_from(&changed, left, top).expect("The changed area lies inside the picture"),
+                    None => self.shown = Some(picture.clone())
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if let Some(frame) = self.pending.take() {
+            self.encoder.write_frame(&frame).map_err(|error| format!("Error writing a GIF frame: {}", error))?;
+        }
+        Ok(())
+    }
+}
+
+/// The included cells whose drawing reaches into each square of a coarse grid over the
+/// picture, to find what to redraw around a cell without looking at every cell.
+struct CellIndex {
+    bounds: Vec<Option<[f32; 4]>>,
+    bucket_size: f32,
+    columns: usize,
+    buckets: Vec<Vec<CellId>>
+}
+
+impl CellIndex {
+    fn new(maze: &Maze, style: &Style, width: u32, height: u32) -> Self {
+        let bounds = cell_bounds(maze, style);
+        let bucket_size = style.cell_size.max(1.0);
+        let columns = (width as f32 / bucket_size).ceil() as usize + 1;
+        let rows = (height as f32 / bucket_size).ceil() as usize + 1;
+        let mut index = Self {
+            bounds,
+            bucket_size,
+            columns,
+            buckets: vec![Vec::new(); columns * rows]
+        };
+        for cell in 0..index.bounds.len() {
+            if let Some(bounds) = index.bounds[cell] {
+                for bucket in index.buckets_under(bounds) {
+                    index.buckets[bucket].push(cell);
+                }
+            }
+        }
+        index
+    }
+
+    fn buckets_under(&self, [left, top, right, bottom]: [f32; 4]) -> Vec<usize> {
+        let rows = self.buckets.len() / self.columns;
+        let range = |low: f32, high: f32, count: usize| {
+            let first = ((low / self.bucket_size).floor().max(0.0) as usize).min(count - 1);
+            let last = ((high / self.bucket_size).floor().max(0.0) as usize).min(count - 1);
+            first..=last
+        };
+        range(top, bottom, rows).flat_map(|row| range(left, right, self.columns).map(move |column| row * self.columns + column)).collect()
+    }
+
+    /// The pixels the drawing of `cell` covers, as left, top, right and bottom within the picture.
+    fn area(&self, cell: CellId, width: u32, height: u32) -> Option<[u32; 4]> {
+        let [left, top, right, bottom] = self.bounds[cell]?;
+        let area = [
+            (left.floor().max(0.0) as u32).min(width),
+            (top.floor().max(0.0) as u32).min(height),
+            (right.ceil().max(0.0) as u32).min(width),
+            (bottom.ceil().max(0.0) as u32).min(height)
+        ];
+        (area[0] < area[2] && area[1] < area[3]).then_some(area)
+    }
+
+    /// Every cell whose drawing reaches into the area.
+    fn cells_in(&self, area: [u32; 4]) -> HashSet<CellId> {
+        let area = area.map(|side| side as f32);
+        self.buckets_under(area)
+            .into_iter()
+            .flat_map(|bucket| self.buckets[bucket].iter().copied())
+            .filter(|cell| self.bounds[*cell].is_some_and(|bounds| bounds[0] < area[2] && bounds[2] > area[0] && bounds[1] < area[3] && bounds[3] > area[1]))
+            .collect()
+    }
+}
+
+/// Records a maze being generated as an animated GIF, one step at a time. The picture is drawn
+/// once and then only redrawn around the cells each step changes, so a step costs the same
+/// however large the maze is.
+pub struct GenerationGif {
+    animation: Animation<BufWriter<File>>,
+    style: Style,
+    picture: RgbaImage,
+    index: CellIndex,
+    /// Where the picture changed since the last frame, as left, top, right and bottom.
+    changed: Option<[u32; 4]>,
+    head: Option<CellId>,
+    steps: usize,
+    frame_skip: usize,
+    frame_delay: u32,
+    wait: u32
+}
+
+impl GenerationGif {
+    /// Starts a GIF of generating `maze` with the backtracker, drawn the way the settings ask.
+    /// A frame is drawn every `gif_frame_skip + 1` steps and shown for
+    /// `gif_frame_delay_in_miliseconds`.
+    pub fn create(path: &str, settings: &Settings, maze: &Maze, backtracker: &Backtracker) -> Result<Self, String> {
+        let style = Style::from_settings(settings);
+        let picture = rasterize(&generation_scene(maze, &style, backtracker));
+        let file = File::create(path).map_err(|error| format!("Error creating the GIF file {}: {}", path, error))?;
+        Ok(Self {
+            animation: Animation::new(BufWriter::new(file), picture.width(), picture.height())?,
+            index: CellIndex::new(maze, &style, picture.width(), picture.height()),
+            style,
+            picture,
+            changed: None,
+            head: backtracker.head(),
+            steps: 0,
+            frame_skip: settings.gif_frame_skip,
+            frame_delay: settings.gif_frame_delay_in_miliseconds,
+            wait: u32::try_from(settings.wait_then_solve_in_miliseconds).unwrap_or(u32::MAX)
+        })
+    }
+
+    /// Redraws the cells the step changed: the ones it carved between and the head before and
+    /// after it.
+    pub fn record(&mut self, maze: &Maze, backtracker: &Backtracker, step: &Step) -> Result<(), String> {
+        let mut cells = match *step {
+            Step::Pushed { from, to, .. } => vec![from, to],
+            Step::Tunnelled { from, under, to, .. } => vec![from, under, to],
+            Step::Popped { to } | Step::Jumped { to } => vec![to],
+            Step::Finished => Vec::new()
+        };
+        cells.extend(self.head);
+        self.head = backtracker.head();
+        cells.sort_unstable();
+        cells.dedup();
+        let (width, height) = (self.picture.width(), self.picture.height());
+        let index = &self.index;
+        let areas = cells.into_iter().filter_map(|cell| index.area(cell, width, height)).collect::<Vec<[u32; 4]>>();
+        for area in areas {
+            let scene = partial_generation_scene(maze, &self.style, backtracker, &self.index.cells_in(area));
+            rasterize_area(&mut self.picture, &scene, area);
+            self.changed = Some(match self.changed {
+                Some(changed) => [changed[0].min(area[0]), changed[1].min(area[1]), changed[2].max(area[2]), changed[3].max(area[3])],
+                None => area
+            });
+        }
+        if self.steps.is_multiple_of(self.frame_skip + 1) {
+            let changed = self.changed.take().unwrap_or([0, 0, 0, 0]);
+            self.animation.push(&self.picture, changed, self.frame_delay)?;
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Ends with the finished maze, drawn the way the style asks for, which stays up for
+    /// `wait_then_solve_in_miliseconds` before the animation loops.
+    pub fn finish(mut self, maze: &Maze) -> Result<(), String> {
+        let picture = rasterize(&scene(maze, &self.style));
+        let whole = [0, 0, picture.width(), picture.height()];
+        self.animation.push(&picture, whole, self.wait)?;
+        self.animation.flush()
+    }
+}
+
+/// Runs a generation to the end, recording it as an animated GIF with `GenerationGif`, and
+/// returns the finished maze.
+pub fn save_generation_gif(mut generation: Generation, path: &str) -> Result<Maze, String> {
+    let mut gif = GenerationGif::create(path, generation.settings(), generation.maze(), generation.backtracker())?;
+    while !generation.is_finished() {
+        let step = generation.step();
+        gif.record(generation.maze(), generation.backtracker(), &step)?;
+    }
+    let maze = generation.finish();
+    gif.finish(&maze)?;
+    Ok(maze)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::samples;
+    use std::env;
+
+    #[test]
+    fn redrawing_around_each_step_matches_drawing_everything() {
+        let path = env::temp_dir().join(format!("maze_maker_test_{}.gif", std::process::id()));
+        let path = path.to_str().unwrap();
+        for (name, sample) in samples() {
+            let layout = sample.topology().layout();
+            let settings = Settings {
+                cell_size: 9.0,
+                wall_thickness: 3.0,
+                background_color: [0.5, 0.5, 0.5, 1.0],
+                ..Settings::default()
+            };
+            let mut generation = Generation::on(layout.build().unwrap(), &Settings { weave: name.contains("weave"), ..settings.clone() }, 3).unwrap();
+            let mut gif = GenerationGif::create(path, &settings, generation.maze(), generation.backtracker()).unwrap();
+            for steps in 1usize.. {
+                if generation.is_finished() {
+                    break;
+                }
+                let step = generation.step();
+                gif.record(generation.maze(), generation.backtracker(), &step).unwrap();
+                // A pixel a redraw misses stays wrong until it is redrawn, so checking every few steps
+                // is enough.
+                if steps.is_multiple_of(5) || generation.is_finished() {
+                    let whole = rasterize(&generation_scene(generation.maze(), &gif.style, generation.backtracker()));
+                    assert!(gif.picture == whole, "{}: the picture differs after {} steps", name, steps);
+                }
+            }
+            gif.finish(&generation.finish()).unwrap();
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use super::{scene, Scene, Shape, Style};
+use crate::maze::Maze;
+use crate::topology::Vector2D;
+use image::{Rgba, RgbaImage};
+use std::f32::consts::PI;
+
+/// Scanlines sampled per pixel row, for smooth edges.
+const SUBSAMPLES: usize = 4;
+
+fn circle(center: Vector2D, radius: f32) -> Vec<Vector2D> {
+    let segments = ((radius * 2.0) as usize).clamp(8, 64);
+    (0..segments)
+        .map(|segment| {
+            let angle = segment as f32 / segments as f32 * 2.0 * PI;
+            Vector2D::new(center.x + radius * angle.cos(), center.y + radius * angle.sin())
+        })
+        .collect()
+}
+
+/// The filled areas making up a shape: a thick line is a quad per segment and a disc at
+/// every point, which gives it round joins and caps.
+fn areas(shape: &Shape) -> (Vec<Vec<Vector2D>>, [f32; 4]) {
+    match shape {
+        Shape::Polygon { points, color } => (vec![points.clone()], *color),
+        Shape::Circle { center, radius, color } => (vec![circle(*center, *radius)], *color),
+        Shape::Line { points, width, color } => {
+            let half = width / 2.0;
+            let mut areas = points.iter().map(|point| circle(*point, half)).collect::<Vec<Vec<Vector2D>>>();
+            for pair in points.windows(2) {
+                let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+                let length = (dx * dx + dy * dy).sqrt();
+                if length == 0.0 {
+                    continue;
+                }
+                let (nx, ny) = (-dy / length * half, dx / length * half);
+                areas.push(vec![
+                    Vector2D::new(pair[0].x + nx, pair[0].y + ny),
+                    Vector2D::new(pair[1].x + nx, pair[1].y + ny),
+                    Vector2D::new(pair[1].x - nx, pair[1].y - ny),
+                    Vector2D::new(pair[0].x - nx, pair[0].y - ny)
+                ]);
+            }
+            (areas, *color)
+        }
+    }
+}
+
+/// How much of each pixel the areas cover, from 0 to 1, over the rows and columns given.
+/// Overlapping areas count once, so a shape blends in a single pass.
+fn coverage(areas: &[Vec<Vector2D>], columns: (usize, usize), rows: (usize, usize)) -> Vec<f32> {
+    let width = columns.1 - columns.0;
+    let mut covered = vec![0.0f32; width * (rows.1 - rows.0)];
+    let mut row_coverage = vec![0.0f32; width];
+    for area in areas.iter().filter(|area| area.len() >= 3) {
+        let (top, bottom) = area.iter().fold((f32::MAX, f32::MIN), |(low, high), point| (low.min(point.y), high.max(point.y)));
+        for row in (top.max(0.0).floor() as usize).max(rows.0)..(bottom.ceil().max(0.0) as usize).min(rows.1) {
+            row_coverage.iter_mut().for_each(|value| *value = 0.0);
+            for sample in 0..SUBSAMPLES {
+                let y = row as f32 + (sample as f32 + 0.5) / SUBSAMPLES as f32;
+                let mut crossings = (0..area.len())
+                    .map(|index| (area[index], area[(index + 1) % area.len()]))
+                    .filter(|(from, to)| (from.y <= y) != (to.y <= y))
+                    .map(|(from, to)| from.x + (y - from.y) / (to.y - from.y) * (to.x - from.x))
+                    .collect::<Vec<f32>>();
+                crossings.sort_by(|first, second| first.partial_cmp(second).unwrap());
+                for span in crossings.chunks_exact(2) {
+                    let (start, end) = (span[0].max(columns.0 as f32), span[1].min(columns.1 as f32));
+                    if start >= end {
+                        continue;
+                    }
+                    for column in start.floor() as usize..(end.ceil() as usize).min(columns.1) {
+                        let inside = end.min(column as f32 + 1.0) - start.max(column as f32);
+                        row_coverage[column - columns.0] += inside / SUBSAMPLES as f32;
+                    }
+                }
+            }
+            let covered_row = &mut covered[(row - rows.0) * width..(row - rows.0 + 1) * width];
+            for (value, new) in covered_row.iter_mut().zip(row_coverage.iter()) {
+                *value = value.max(new.min(1.0));
+            }
+        }
+    }
+    covered
+}
+
+/// Blends the areas into the pixels of `clip`, given as left, top, right and bottom. Every pixel
+/// comes out the same whatever the clip, as long as it holds the pixel.
+fn fill(image: &mut RgbaImage, areas: &[Vec<Vector2D>], color: [f32; 4], clip: [u32; 4]) {
+    let points = areas.iter().flatten();
+    let (left, right) = points.clone().fold((f32::MAX, f32::MIN), |(low, high), point| (low.min(point.x), high.max(point.x)));
+    let (top, bottom) = points.fold((f32::MAX, f32::MIN), |(low, high), point| (low.min(point.y), high.max(point.y)));
+    let columns = ((left.max(0.0).floor() as usize).max(clip[0] as usize), (right.ceil().max(0.0) as usize).min(clip[2] as usize));
+    let rows = ((top.max(0.0).floor() as usize).max(clip[1] as usize), (bottom.ceil().max(0.0) as usize).min(clip[3] as usize));
+    if columns.0 >= columns.1 || rows.0 >= rows.1 {
+        return;
+    }
+    let covered = coverage(areas, columns, rows);
+    let width = columns.1 - columns.0;
+    for row in rows.0..rows.1 {
+        for column in columns.0..columns.1 {
+            let alpha = covered[(row - rows.0) * width + column - columns.0] * color[3].clamp(0.0, 1.0);
+            if alpha <= 0.0 {
+                continue;
+            }
+            let pixel = image.get_pixel_mut(column as u32, row as u32);
+            for (channel, over) in pixel.0.iter_mut().zip(color.iter()).take(3) {
+                let under = *channel as f32 / 255.0;
+                *channel = ((under + (over.clamp(0.0, 1.0) - under) * alpha) * 255.0).round() as u8;
+            }
+            let under = pixel.0[3] as f32 / 255.0;
+            pixel.0[3] = ((under + (1.0 - under) * alpha) * 255.0).round() as u8;
+        }
+    }
+}
+
+/// Draws a scene on the CPU, without a window or a graphics card.
+pub(super) fn rasterize(scene: &Scene) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(scene.size.x.ceil() as u32, scene.size.y.ceil() as u32, Rgba([0, 0, 0, 0]));
+    let clip = [0, 0, image.width(), image.height()];
+    rasterize_area(&mut image, scene, clip);
+    image
+}
+
+/// Draws a scene over the pixels of `area` (left, top, right and bottom) only, clearing them
+/// first. The scene needs every shape that reaches into the area but may leave out the rest;
+/// the pixels come out as `rasterize` would draw them.
+pub(super) fn rasterize_area(image: &mut RgbaImage, scene: &Scene, area: [u32; 4]) {
+    for y in area[1]..area[3] {
+        for x in area[0]..area[2] {
+            image.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+        }
+    }
+    for shape in scene.layers.iter().flat_map(|layer| layer.shapes.iter()) {
+        let (areas, color) = areas(shape);
+        fill(image, &areas, color, area);
+    }
+}
+
+pub fn to_image(maze: &Maze, style: &Style) -> RgbaImage {
+    rasterize(&scene(maze, style))
+}
+
+pub fn save_png(maze: &Maze, style: &Style, path: &str) -> Result<(), String> {
+    to_image(maze, style)
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|error| format!("Error writing the PNG file {}: {}", path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::generated;
+    use std::{env, fs};
+
+    #[test]
+    fn draws_walls_over_the_cells() {
+        let maze = generated("table_size:2x2", 3);
+        let style = Style { solution: false, openings: false, ..Style::default() };
+        let image = to_image(&maze, &style);
+        assert_eq!(image.dimensions(), (60, 60));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([128, 128, 128, 255]));
+        assert_eq!(image.get_pixel(59, 59), &Rgba([128, 128, 128, 255]));
+        assert_eq!(image.get_pixel(10, 10), &Rgba([0, 0, 0, 255]));
+
+        let topology = maze.topology();
+        for cell in 0..topology.cell_count() {
+            let center = topology.cell_center(cell);
+            let center = image.get_pixel((center.x * 20.0 + 10.0) as u32, (center.y * 20.0 + 10.0) as u32);
+            assert_eq!(center, &Rgba([255, 255, 255, 255]), "cell {}", cell);
+            for direction in 0..topology.direction_count(cell) {
+                let wall = topology.wall(cell, direction);
+                let (x, y) = ((wall[0].x + wall[1].x) * 10.0 + 10.0, (wall[0].y + wall[1].y) * 10.0 + 10.0);
+                let expected = if maze.has_wall(cell, direction) { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) };
+                assert_eq!(image.get_pixel(x as u32, y as u32), &expected, "cell {} direction {}", cell, direction);
+            }
+        }
+    }
+
+    #[test]
+    fn saves_what_it_draws() {
+        let maze = generated("table_size:2x3", 5);
+        let style = Style::default();
+        let path = env::temp_dir().join(format!("maze_maker_test_png_{}.png", std::process::id()));
+        save_png(&maze, &style, path.to_str().unwrap()).unwrap();
+        let saved = image::open(&path).unwrap().to_rgba8();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved, to_image(&maze, &style));
+    }
+}
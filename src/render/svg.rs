@@ -0,0 +1,103 @@
+use super::{scene, Shape, Style};
+use crate::maze::Maze;
+use crate::topology::Vector2D;
+use std::fmt::Write;
+
+fn points(points: &[Vector2D]) -> String {
+    points.iter().map(|point| format!("{:.2},{:.2}", point.x, point.y)).collect::<Vec<String>>().join(" ")
+}
+
+/// A `fill` or `stroke` attribute with its opacity.
+fn paint(attribute: &str, color: [f32; 4]) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "{0}=\"rgb({1},{2},{3})\" {0}-opacity=\"{4:.3}\"",
+        attribute,
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        color[3].clamp(0.0, 1.0)
+    )
+}
+
+/// The maze as an SVG document, one group per layer so each can be hidden in an editor.
+pub fn to_svg(maze: &Maze, style: &Style) -> String {
+    let scene = scene(maze, style);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.2}\" height=\"{1:.2}\" viewBox=\"0 0 {0:.2} {1:.2}\">",
+        scene.size.x,
+        scene.size.y
+    ).unwrap();
+    for layer in scene.layers.iter() {
+        writeln!(svg, "<g id=\"{}\">", layer.name).unwrap();
+        for shape in layer.shapes.iter() {
+            match shape {
+                Shape::Polygon { points: corners, color } => writeln!(
+                    svg,
+                    "<polygon points=\"{}\" {}/>",
+                    points(corners),
+                    paint("fill", *color)
+                ),
+                Shape::Line { points: path, width, color } => writeln!(
+                    svg,
+                    "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{:.2}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                    points(path),
+                    paint("stroke", *color),
+                    width
+                ),
+                Shape::Circle { center, radius, color } => writeln!(
+                    svg,
+                    "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+                    center.x,
+                    center.y,
+                    radius,
+                    paint("fill", *color)
+                )
+            }.unwrap();
+        }
+        writeln!(svg, "</g>").unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::generated;
+
+    #[test]
+    fn writes_a_group_per_layer() {
+        let maze = generated("table_size:2x2", 3);
+        let style = Style::default();
+        let svg = to_svg(&maze, &style);
+        let lines = svg.lines().collect::<Vec<&str>>();
+        assert!(lines[0].starts_with("<svg ") && lines[0].ends_with('>'));
+        assert_eq!(lines.last(), Some(&"</svg>"));
+
+        let mut groups = Vec::new();
+        let mut open = None;
+        for line in &lines[1..lines.len() - 1] {
+            if let Some(name) = line.strip_prefix("<g id=\"").and_then(|rest| rest.strip_suffix("\">")) {
+                assert!(open.is_none(), "{} opens inside another group", name);
+                open = Some(name);
+                groups.push((name, Vec::new()));
+            } else if *line == "</g>" {
+                assert!(open.take().is_some(), "a group closes twice");
+            } else {
+                assert!(open.is_some() && line.starts_with('<') && line.ends_with("/>"), "{}", line);
+                groups.last_mut().unwrap().1.push(*line);
+            }
+        }
+        assert!(open.is_none());
+        let names = scene(&maze, &style).layers.iter().map(|layer| layer.name).collect::<Vec<&str>>();
+        assert_eq!(groups.iter().map(|(name, _)| *name).collect::<Vec<&str>>(), names);
+
+        // Twelve walls in a 2×2 grid, less three passages, the entrance and the exit.
+        let walls = &groups.iter().find(|(name, _)| *name == "walls").unwrap().1;
+        assert_eq!(walls.len(), 7);
+        assert!(walls.iter().all(|wall| wall.starts_with("<polyline ")));
+    }
+}
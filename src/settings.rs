@@ -0,0 +1,173 @@
+use crate::mask::Mask;
+use crate::maze::Opening;
+use crate::topology::{FloorView, Layout, Side, Topology, TopologyKind, Wrap};
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Everything `settings.conf` can set. The file holds one `key:value` pair per line; keys that
+/// are left out keep their default.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub table: [u32; 2],
+    pub background_color: [f32; 4],
+    pub foreground_color: [f32; 4],
+    pub refresh_rate_in_miliseconds: u64,
+    pub wait_then_solve_in_miliseconds: u64,
+    pub entrance: Opening,
+    pub exit: Opening,
+    pub topology: TopologyKind,
+    pub mask: Option<String>,
+    pub floors: u8,
+    pub floor_view: FloorView,
+    pub wrap: Wrap,
+    pub weave: bool,
+    pub instant: bool,
+    pub cell_size: f32,
+    pub margin: f32,
+    pub wall_thickness: f32,
+    pub show_solution: bool,
+    pub show_openings: bool,
+    pub show_heatmap: bool,
+    pub gif_frame_skip: usize,
+    pub gif_frame_delay_in_miliseconds: u32,
+    pub event_log: Option<String>
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            table: [20, 20],
+            background_color: [0.5, 0.5, 0.5, 1.0],
+            foreground_color: [1.0, 1.0, 1.0, 1.0],
+            refresh_rate_in_miliseconds: 200,
+            wait_then_solve_in_miliseconds: 2000,
+            entrance: Opening::At(Side::Left, 0),
+            exit: Opening::FarthestApart,
+            topology: TopologyKind::Rectangular,
+            mask: None,
+            floors: 1,
+            floor_view: FloorView::SideBySide,
+            wrap: Wrap::None,
+            weave: false,
+            instant: false,
+            cell_size: 20.0,
+            margin: 10.0,
+            wall_thickness: 2.0,
+            show_solution: true,
+            show_openings: true,
+            show_heatmap: false,
+            gif_frame_skip: 0,
+            gif_frame_delay_in_miliseconds: 50,
+            event_log: None
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut settings = String::new();
+        File::open(path)
+            .map_err(|error| format!("Error opening the {} file: {}", path, error))?
+            .read_to_string(&mut settings)
+            .map_err(|error| format!("Error reading the {} file: {}", path, error))?;
+        settings.parse::<Settings>()
+    }
+
+    /// The configured topology, cut to the mask if there is one and stacked into floors if
+    /// there are several. A mask decides the table size by itself.
+    pub fn layout(&self) -> Result<Layout, String> {
+        let mut layout = Layout {
+            wrap: self.wrap,
+            floors: self.floors,
+            floor_view: self.floor_view,
+            ..Layout::new(self.topology, self.table[0], self.table[1])
+        };
+        if let Some(path) = &self.mask {
+            let mask = Mask::load(path)?;
+            layout.rows = mask.table()[0];
+            layout.columns = mask.table()[1];
+            layout.mask = Some(mask.to_ascii());
+        }
+        Ok(layout)
+    }
+
+    pub fn build_topology(&self) -> Result<Box<dyn Topology>, String> {
+        if self.weave && (self.topology != TopologyKind::Rectangular || self.floors > 1) {
+            return Err(String::from("Only single floor rectangular tables can weave"));
+        }
+        self.layout()?.build()
+    }
+}
+
+fn parse_number<T: FromStr>(key: &str, value: &str) -> Result<T, String> where T::Err: std::fmt::Display {
+    value.trim().parse::<T>().map_err(|error| format!("Invalid {} \"{}\": {}", key, value, error))
+}
+
+fn parse_color(key: &str, value: &str) -> Result<[f32; 4], String> {
+    let channels = value.split(',').collect::<Vec<&str>>();
+    if channels.len() != 4 {
+        return Err(format!("Expected four comma separated channels for {} but found \"{}\"", key, value));
+    }
+    let mut color = [0.0; 4];
+    for (channel, text) in color.iter_mut().zip(channels) {
+        *channel = parse_number::<f32>(key, text)?;
+    }
+    Ok(color)
+}
+
+impl FromStr for Settings {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut settings = Settings::default();
+        for row in text.lines().map(|row| row.trim()).filter(|row| !row.is_empty()) {
+            let (key, value) = row.split_once(':').ok_or_else(|| format!("Expected \"key:value\" but found \"{}\"", row))?;
+            match key.trim() {
+                "table_size" => {
+                    let table_size = value.split('x').collect::<Vec<&str>>();
+                    if table_size.len() != 2 {
+                        return Err(format!("Expected \"<rows>x<columns>\" for table_size but found \"{}\"", value));
+                    }
+                    settings.table = [parse_number::<u32>(key, table_size[0])?, parse_number::<u32>(key, table_size[1])?];
+                    if settings.table.contains(&0) {
+                        return Err(format!("table_size must be at least 1x1 but found \"{}\"", value));
+                    }
+                },
+                "background_color" => settings.background_color = parse_color(key, value)?,
+                "foreground_color" => settings.foreground_color = parse_color(key, value)?,
+                "refresh_rate_in_miliseconds" => settings.refresh_rate_in_miliseconds = parse_number::<u64>(key, value)?,
+                "wait_then_solve_in_miliseconds" => settings.wait_then_solve_in_miliseconds = parse_number::<u64>(key, value)?,
+                "entrance" => settings.entrance = value.parse::<Opening>()?,
+                "exit" => settings.exit = value.parse::<Opening>()?,
+                "topology" => settings.topology = value.parse::<TopologyKind>()?,
+                "mask" => settings.mask = Some(String::from(value.trim())).filter(|path| !path.is_empty()),
+                "floors" => {
+                    settings.floors = parse_number::<u8>(key, value)?;
+                    if settings.floors == 0 {
+                        return Err(String::from("floors must be at least 1"));
+                    }
+                },
+                "floor_view" => settings.floor_view = value.parse::<FloorView>()?,
+                "wrap" => settings.wrap = value.parse::<Wrap>()?,
+                "weave" => settings.weave = parse_number::<bool>(key, value)?,
+                "instant" => settings.instant = parse_number::<bool>(key, value)?,
+                "cell_size" => settings.cell_size = parse_number::<f32>(key, value)?,
+                "margin" => settings.margin = parse_number::<f32>(key, value)?,
+                "wall_thickness" => settings.wall_thickness = parse_number::<f32>(key, value)?,
+                "show_solution" => settings.show_solution = parse_number::<bool>(key, value)?,
+                "show_openings" => settings.show_openings = parse_number::<bool>(key, value)?,
+                "show_heatmap" => settings.show_heatmap = parse_number::<bool>(key, value)?,
+                "gif_frame_skip" => settings.gif_frame_skip = parse_number::<usize>(key, value)?,
+                "gif_frame_delay_in_miliseconds" => settings.gif_frame_delay_in_miliseconds = parse_number::<u32>(key, value)?,
+                "event_log" => settings.event_log = Some(String::from(value.trim())).filter(|path| !path.is_empty()),
+                other => return Err(format!("Unknown setting \"{}\"", other))
+            }
+        }
+        if settings.cell_size <= 0.0 || settings.margin < 0.0 || settings.wall_thickness < 0.0 {
+            return Err(String::from("cell_size must be positive, and margin and wall_thickness cannot be negative"));
+        }
+        settings.topology.check(settings.table)?;
+        Ok(settings)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::maze::Maze;
+use crate::topology::CellId;
+use std::collections::VecDeque;
+
+/// The shortest way from `from` to `to` following the maze's links, both ends included. A
+/// tunnel under a crossing is a single move, so the cell it passes under is not on the path.
+pub fn shortest_path(maze: &Maze, from: CellId, to: CellId) -> Option<Vec<CellId>> {
+    let mut previous = vec![None; maze.topology().cell_count()];
+    previous[from] = Some(from);
+    let mut queue = VecDeque::from(vec![from]);
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            let mut path = vec![to];
+            let mut cell = to;
+            while cell != from {
+                cell = previous[cell].unwrap();
+                path.push(cell);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in maze.links(cell) {
+            if previous[next].is_none() {
+                previous[next] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// The path from the entrance to the exit, once both have been made.
+pub fn solve(maze: &Maze) -> Option<Vec<CellId>> {
+    shortest_path(maze, maze.entrance()?.0, maze.exit()?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::tests::{generated, samples};
+    use crate::topology::rectangular::{DOWN, LEFT, RIGHT, UP};
+    use crate::topology::Rectangular;
+
+    #[test]
+    fn solves_generated_mazes_from_entrance_to_exit() {
+        let mut mazes = samples();
+        mazes.extend((0..8).map(|seed| (format!("weave seed {}", seed), generated("weave:true\ntable_size:12x12\nentrance:random\nexit:random", seed))));
+        assert!(mazes.iter().any(|(_, maze)| (0..maze.topology().cell_count()).any(|cell| maze.crossing(cell).is_some())));
+        for (name, maze) in mazes {
+            let (Some((entrance, _)), Some((exit, _))) = (maze.entrance(), maze.exit()) else {
+                continue;
+            };
+            let path = solve(&maze).unwrap_or_else(|| panic!("{}: no way through", name));
+            assert_eq!((path.first(), path.last()), (Some(&entrance), Some(&exit)), "{}", name);
+            for step in path.windows(2) {
+                assert!(maze.links(step[0]).contains(&step[1]), "{}: {} does not lead to {}", name, step[0], step[1]);
+            }
+            assert_eq!(path.len() as u32 - 1, maze.distances_from(entrance)[exit], "{}", name);
+        }
+    }
+
+    #[test]
+    fn a_tunnel_and_the_passage_over_it_are_separate() {
+        // A 3x3 grid with a passage down the middle column over a tunnel along the middle row.
+        let mut maze = Maze::new(Box::new(Rectangular::new(3, 3)));
+        maze.carve(1, DOWN);
+        maze.carve(4, DOWN);
+        assert_eq!(maze.tunnel(3, RIGHT), 5);
+        assert_eq!(maze.crossing(4), Some(RIGHT));
+        assert_eq!(shortest_path(&maze, 3, 5), Some(vec![3, 5]));
+        assert_eq!(shortest_path(&maze, 1, 7), Some(vec![1, 4, 7]));
+        assert_eq!(shortest_path(&maze, 3, 4), None);
+        assert_eq!(shortest_path(&maze, 5, 7), None);
+        assert!(maze.link(4, LEFT).is_none() && maze.link(4, UP) == Some(1));
+    }
+
+    #[test]
+    fn needs_an_entrance_and_exit() {
+        let mut maze = generated("table_size:4x4", 0);
+        assert!(solve(&maze).is_some());
+        maze = Maze::new(Box::new(Rectangular::new(4, 4)));
+        assert_eq!(solve(&maze), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::generator::Backtracker;
+use crate::maze::Maze;
+use crate::topology::rectangular::{DOWN, LEFT, RIGHT, UP};
+use crate::topology::{CellId, Direction};
+use std::collections::HashSet;
+
+/// Background color of the solution path.
+const PATH_COLOR: &str = "\x1b[42m";
+const RESET: &str = "\x1b[0m";
+/// Cells the generator has not reached yet.
+const UNVISITED: &str = "\x1b[90m░░░\x1b[0m";
+/// The cell the generator is at.
+const HEAD: &str = "\x1b[1;31m ● \x1b[0m";
+
+/// The box-drawing character where the walls going up, right, down and left meet, indexed by
+/// those four as bits 0 to 3.
+const CORNERS: [char; 16] = [' ', '╵', '╶', '└', '╷', '│', '┌', '├', '╴', '┘', '─', '┴', '┐', '┤', '┬', '┼'];
+
+/// Draws a square grid with Unicode box-drawing characters for the terminal. Cells are three
+/// characters wide and one line tall, and the entrance and exit are gaps in the outer wall. A
+/// cell a tunnel runs under holds ` ║ ` when the passage on top runs up and down, or `═══` when
+/// it runs across.
+///
+/// ```text
+/// ┌───────────╴
+/// │
+/// └───────╴   ╷
+///             │
+/// ╶───────────┘
+/// ```
+///
+/// With a `solution`, the cells on it and the gaps between them are colored with ANSI escape
+/// codes. A tunnel on the path shows as colored gaps on both sides of the cell it runs under.
+/// The solution can also be the first part of the way, from the entrance on.
+pub fn render(maze: &Maze, solution: Option<&[CellId]>) -> Result<String, String> {
+    draw(maze, solution.unwrap_or(&[]), |_| None)
+}
+
+/// Draws a maze that is still being generated, with the cells the generator has not reached
+/// shaded and the cell it is at marked.
+pub fn render_generation(maze: &Maze, backtracker: &Backtracker) -> Result<String, String> {
+    draw(maze, &[], |cell| match backtracker.head() {
+        Some(head) if head == cell => Some(HEAD),
+        _ if !backtracker.is_visited(cell) => Some(UNVISITED),
+        _ => None
+    })
+}
+
+/// Draws the maze with `path` colored in. `fill` can replace what is drawn inside a cell.
+fn draw(maze: &Maze, path: &[CellId], fill: impl Fn(CellId) -> Option<&'static str>) -> Result<String, String> {
+    let [rows, columns] = maze.topology().grid().ok_or_else(|| String::from("Only rectangular mazes that do not wrap around can be drawn in the terminal"))?;
+    let (rows, columns) = (rows as usize, columns as usize);
+    let topology = maze.topology();
+    let cell = |row: usize, column: usize| row * columns + column;
+    let horizontal = |line: usize, column: usize| if line < rows { (cell(line, column), UP) } else { (cell(rows - 1, column), DOWN) };
+    let vertical = |row: usize, line: usize| if line < columns { (cell(row, line), LEFT) } else { (cell(row, columns - 1), RIGHT) };
+    let drawn = |(cell, direction): (CellId, Direction)| maze.has_wall(cell, direction);
+
+    let cells = path.iter().copied().collect::<HashSet<CellId>>();
+    let sides = path_sides(maze, path);
+    let on_path = |(cell, direction): (CellId, Direction)| {
+        sides.contains(&(cell, direction))
+            || topology.neighbour(cell, direction).is_some_and(|neighbour| sides.contains(&(neighbour, topology.opposite(cell, direction))))
+    };
+    let paint = |text: &mut String, symbols: &str, colored: bool| {
+        if colored {
+            text.push_str(PATH_COLOR);
+            text.push_str(symbols);
+            text.push_str(RESET);
+        } else {
+            text.push_str(symbols);
+        }
+    };
+
+    let mut text = String::new();
+    for line in 0..=rows {
+        for column in 0..=columns {
+            let up = line > 0 && drawn(vertical(line - 1, column));
+            let right = column < columns && drawn(horizontal(line, column));
+            let down = line < rows && drawn(vertical(line, column));
+            let left = column > 0 && drawn(horizontal(line, column - 1));
+            text.push(CORNERS[up as usize | (right as usize) << 1 | (down as usize) << 2 | (left as usize) << 3]);
+            if column < columns {
+                let wall = horizontal(line, column);
+                paint(&mut text, if drawn(wall) { "───" } else { "   " }, !drawn(wall) && on_path(wall));
+            }
+        }
+        text.push('\n');
+        if line == rows {
+            break;
+        }
+        for column in 0..=columns {
+            let wall = vertical(line, column);
+            paint(&mut text, if drawn(wall) { "│" } else { " " }, !drawn(wall) && on_path(wall));
+            if column < columns {
+                if let Some(symbols) = fill(cell(line, column)) {
+                    text.push_str(symbols);
+                    continue;
+                }
+                let symbols = match maze.crossing(cell(line, column)) {
+                    Some(UP) => "═══",
+                    Some(_) => " ║ ",
+                    None => "   "
+                };
+                paint(&mut text, symbols, cells.contains(&cell(line, column)));
+            }
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// The sides the path leaves its cells through, the cells it tunnels under included, plus the
+/// entrance and exit when it reaches them.
+fn path_sides(maze: &Maze, path: &[CellId]) -> HashSet<(CellId, Direction)> {
+    let topology = maze.topology();
+    let entrance = maze.entrance().filter(|(cell, _)| path.first() == Some(cell));
+    let exit = maze.exit().filter(|(cell, _)| path.last() == Some(cell));
+    let mut sides = entrance.into_iter().chain(exit).collect::<HashSet<(CellId, Direction)>>();
+    for step in path.windows(2) {
+        let (from, to) = (step[0], step[1]);
+        if let Some(direction) = (0..topology.direction_count(from)).find(|direction| maze.link(from, *direction) == Some(to)) {
+            sides.insert((from, direction));
+            let next = topology.neighbour(from, direction).unwrap();
+            if next != to {
+                let exit = topology.straight_on(next, topology.opposite(from, direction)).unwrap();
+                sides.insert((next, exit));
+            }
+        }
+    }
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii;
+    use crate::solver;
+
+    const MAZE: &str = "+--+--+\nE     |\n+--+  +\n|     X\n+--+--+\n";
+
+    #[test]
+    fn draws_corners_and_openings() {
+        let maze = ascii::import(MAZE).unwrap();
+        let expected = "╶───────┐\n        │\n┌───╴   ╵\n│        \n└───────╴\n";
+        assert_eq!(render(&maze, None).unwrap(), expected);
+    }
+
+    #[test]
+    fn colors_the_solution() {
+        let maze = ascii::import(MAZE).unwrap();
+        let solution = solver::solve(&maze).unwrap();
+        assert_eq!(solution, [0, 1, 3]);
+        let path = |symbols: &str| format!("{}{}{}", PATH_COLOR, symbols, RESET);
+        let expected = [
+            String::from("╶───────┐"),
+            format!("{}{}{}{}│", path(" "), path("   "), path(" "), path("   ")),
+            format!("┌───╴{}╵", path("   ")),
+            format!("│    {}{}", path("   "), path(" ")),
+            String::from("└───────╴")
+        ];
+        assert_eq!(render(&maze, Some(&solution)).unwrap(), expected.join("\n") + "\n");
+    }
+}
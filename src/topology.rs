@@ -0,0 +1,437 @@
+mod hexagonal;
+mod layered;
+mod polar;
+pub mod rectangular;
+mod triangular;
+
+pub use hexagonal::Hexagonal;
+pub use layered::{FloorView, Layered};
+pub use polar::Polar;
+pub use rectangular::{Rectangular, Wrap};
+pub use triangular::Triangular;
+
+use crate::mask::{Mask, Masked};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub type CellId = usize;
+
+/// Index of one side of a cell, in the order its topology lists them.
+pub type Direction = usize;
+
+/// No cell has more sides than this.
+pub const MAX_DIRECTIONS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector2D {
+    pub x: f32,
+    pub y: f32
+}
+
+impl Vector2D {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Top, Side::Right, Side::Bottom, Side::Left];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stairs {
+    Up,
+    Down
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim() {
+            "top" => Ok(Side::Top),
+            "right" => Ok(Side::Right),
+            "bottom" => Ok(Side::Bottom),
+            "left" => Ok(Side::Left),
+            other => Err(format!("Unknown side \"{}\"", other))
+        }
+    }
+}
+
+/// The shape of a maze: how many cells it has, which cells touch, and where everything is
+/// drawn. Geometry is in layout units; renderers scale `size()` to whatever they draw on.
+/// Topologies are plain data, so mazes can be built on worker threads.
+pub trait Topology: Send + Sync {
+    /// The description this topology can be rebuilt from.
+    fn layout(&self) -> Layout;
+
+    /// A copy to build another maze on, without rebuilding it from its layout.
+    fn boxed_clone(&self) -> Box<dyn Topology>;
+
+    fn cell_count(&self) -> usize;
+
+    /// Cells cut out of the layout keep their id but are never visited or drawn.
+    fn is_included(&self, _cell: CellId) -> bool {
+        true
+    }
+
+    fn direction_count(&self, cell: CellId) -> usize;
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId>;
+
+    /// How many slots a packed set of walls needs, one for every wall two cells share.
+    fn wall_count(&self) -> usize {
+        self.cell_count() * MAX_DIRECTIONS
+    }
+
+    /// The slot of the wall between `cell` and its neighbour in `direction`, the same seen from
+    /// either side. Outer walls have none. By default the wall belongs to whichever side has
+    /// the lower cell id, so every cell reserves `MAX_DIRECTIONS` slots; grids override this to
+    /// give each cell only the walls it owns.
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        let neighbour = self.neighbour(cell, direction)?;
+        let (owner, side) = (cell, direction).min((neighbour, self.opposite(cell, direction)));
+        Some(owner * MAX_DIRECTIONS + side)
+    }
+
+    /// The direction that leads from `neighbour(cell, direction)` back to `cell`.
+    fn opposite(&self, cell: CellId, direction: Direction) -> Direction {
+        let neighbour = self.neighbour(cell, direction).expect("Border walls have no opposite side");
+        (0..self.direction_count(neighbour))
+            .find(|other| self.neighbour(neighbour, *other) == Some(cell))
+            .expect("Neighbours must point back at each other")
+    }
+
+    /// Passages that leave the drawing plane have no wall to draw; renderers mark them instead.
+    fn stairs(&self, _cell: CellId, _direction: Direction) -> Option<Stairs> {
+        None
+    }
+
+    /// The side across the cell from `direction`, where a passage entering through `direction`
+    /// would leave if it went straight on. Only topologies that support weaving have one.
+    fn straight_on(&self, _cell: CellId, _direction: Direction) -> Option<Direction> {
+        None
+    }
+
+    /// Rows and columns, for plain grids of square cells numbered row by row with sides up,
+    /// right, down and left, whose edges do not wrap. Text formats only know how to lay out
+    /// these.
+    fn grid(&self) -> Option<[u32; 2]> {
+        None
+    }
+
+    /// An arrow to draw on a wall that leads across the edge of the layout, pointing the way its
+    /// edge is glued to the opposite one. Walls that do not wrap get `None`.
+    fn wrap_marker(&self, _cell: CellId, _direction: Direction) -> Option<Vec<Vector2D>> {
+        None
+    }
+
+    fn floor_count(&self) -> usize {
+        1
+    }
+
+    fn floor(&self, _cell: CellId) -> usize {
+        0
+    }
+
+    /// Outer walls along one side of the layout, one per border cell.
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)>;
+
+    fn size(&self) -> Vector2D;
+
+    fn cell_center(&self, cell: CellId) -> Vector2D;
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D>;
+
+    /// The wall on one side of a cell as a polyline, empty for stairs.
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D>;
+}
+
+impl Clone for Box<dyn Topology> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Shapes for drawing a weave crossing in `cell`: a band for the passage running under it along
+/// `under`, and a band for the passage over it. The long sides of the over band are its walls.
+pub fn crossing_bands(topology: &dyn Topology, cell: CellId, under: Direction) -> [Vec<Vector2D>; 2] {
+    let band = |direction: Direction| {
+        let across = topology.straight_on(cell, direction).expect("Crossings need a topology that supports weaving");
+        let (near, far) = (topology.wall(cell, direction), topology.wall(cell, across));
+        let narrowed = |wall: &[Vector2D], from: f32| Vector2D::new(
+            wall[0].x + (wall[1].x - wall[0].x) * from,
+            wall[0].y + (wall[1].y - wall[0].y) * from
+        );
+        vec![narrowed(&near, 0.25), narrowed(&near, 0.75), narrowed(&far, 0.25), narrowed(&far, 0.75)]
+    };
+    let over = (0..topology.direction_count(cell))
+        .find(|direction| *direction != under && Some(*direction) != topology.straight_on(cell, under))
+        .unwrap();
+    [band(under), band(over)]
+}
+
+/// Whether a tunnel can run under `cell` through side `under` while a passage crosses over it:
+/// both need a neighbour on either side of the cell.
+pub fn can_cross(topology: &dyn Topology, cell: CellId, under: Direction) -> bool {
+    if cell >= topology.cell_count() || !topology.is_included(cell) || under >= topology.direction_count(cell) {
+        return false;
+    }
+    let through = |direction: Direction| {
+        topology.neighbour(cell, direction).is_some() && topology.straight_on(cell, direction).is_some_and(|across| topology.neighbour(cell, across).is_some())
+    };
+    through(under) && (0..topology.direction_count(cell)).any(|over| over != under && Some(over) != topology.straight_on(cell, under) && through(over))
+}
+
+/// The grid shapes `settings.conf` can pick. Polar mazes take their ring count from the rows of
+/// `table_size` and ignore the columns.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopologyKind {
+    Rectangular,
+    Hexagonal,
+    Triangular,
+    Polar
+}
+
+impl TopologyKind {
+    pub fn check(&self, table: [u32; 2]) -> Result<(), String> {
+        match self {
+            TopologyKind::Triangular if table[0] > 1 && table[1] < 2 => {
+                Err(String::from("A triangular table needs at least two columns, otherwise its rows do not touch"))
+            },
+            _ => Ok(())
+        }
+    }
+
+    pub fn build(&self, table: [u32; 2]) -> Box<dyn Topology> {
+        match self {
+            TopologyKind::Rectangular => Box::new(Rectangular::new(table[0], table[1])),
+            TopologyKind::Hexagonal => Box::new(Hexagonal::new(table[0], table[1])),
+            TopologyKind::Triangular => Box::new(Triangular::new(table[0], table[1])),
+            TopologyKind::Polar => Box::new(Polar::new(table[0]))
+        }
+    }
+}
+
+impl FromStr for TopologyKind {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim() {
+            "rectangular" => Ok(TopologyKind::Rectangular),
+            "hexagonal" => Ok(TopologyKind::Hexagonal),
+            "triangular" => Ok(TopologyKind::Triangular),
+            "polar" => Ok(TopologyKind::Polar),
+            other => Err(format!("Unknown topology \"{}\"", other))
+        }
+    }
+}
+
+impl fmt::Display for TopologyKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            TopologyKind::Rectangular => "rectangular",
+            TopologyKind::Hexagonal => "hexagonal",
+            TopologyKind::Triangular => "triangular",
+            TopologyKind::Polar => "polar"
+        })
+    }
+}
+
+fn one_floor() -> u8 {
+    1
+}
+
+/// Everything needed to rebuild a topology, as saved in maze files: the grid shape and size,
+/// how its edges wrap, which cells a mask leaves out (as the rows of an ASCII mask), and how
+/// many floors are stacked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub kind: TopologyKind,
+    pub rows: u32,
+    pub columns: u32,
+    #[serde(default)]
+    pub wrap: Wrap,
+    #[serde(default)]
+    pub mask: Option<Vec<String>>,
+    #[serde(default = "one_floor")]
+    pub floors: u8,
+    #[serde(default)]
+    pub floor_view: FloorView
+}
+
+impl Layout {
+    /// A single floor of `kind` with nothing wrapped or masked.
+    pub fn new(kind: TopologyKind, rows: u32, columns: u32) -> Self {
+        Self {
+            kind,
+            rows,
+            columns,
+            wrap: Wrap::None,
+            mask: None,
+            floors: 1,
+            floor_view: FloorView::SideBySide
+        }
+    }
+
+    pub fn build(&self) -> Result<Box<dyn Topology>, String> {
+        if self.rows == 0 || self.columns == 0 {
+            return Err(format!("A table must be at least 1x1 but this one is {}x{}", self.rows, self.columns));
+        }
+        if self.floors == 0 {
+            return Err(String::from("A maze needs at least one floor"));
+        }
+        if self.wrap != Wrap::None && self.kind != TopologyKind::Rectangular {
+            return Err(String::from("Only rectangular tables can wrap around"));
+        }
+        // Glued to itself, a cell would be its own neighbour.
+        if self.wrap != Wrap::None && self.columns < 2 {
+            return Err(format!("A table must be at least 2 columns wide to wrap around but this one is {}x{}", self.rows, self.columns));
+        }
+        if matches!(self.wrap, Wrap::Torus | Wrap::Klein) && self.rows < 2 {
+            return Err(format!("A table must be at least 2 rows tall to wrap top to bottom but this one is {}x{}", self.rows, self.columns));
+        }
+        self.kind.check([self.rows, self.columns])?;
+        let table: Box<dyn Topology> = match self.kind {
+            TopologyKind::Rectangular => Box::new(Rectangular::with_wrap(self.rows, self.columns, self.wrap)),
+            other => other.build([self.rows, self.columns])
+        };
+        let floor: Box<dyn Topology> = match &self.mask {
+            Some(rows) => {
+                if self.kind == TopologyKind::Polar {
+                    return Err(String::from("Masks only work with row-and-column topologies, not polar ones"));
+                }
+                let mask = Mask::from_ascii(&rows.join("\n"))?;
+                if mask.table() != [self.rows, self.columns] {
+                    return Err(format!("The mask is {}x{} but the table is {}x{}", mask.table()[0], mask.table()[1], self.rows, self.columns));
+                }
+                Box::new(Masked::new(table, mask))
+            },
+            None => table
+        };
+        if self.floors > 1 {
+            return Ok(Box::new(Layered::new(floor, self.floors, self.floor_view)));
+        }
+        Ok(floor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    /// Checks what every topology promises: neighbours point back at each other through
+    /// `opposite`, both sides of a wall share one slot that no other wall uses, outer walls have
+    /// none, and `straight_on` goes both ways.
+    fn check(layout: &Layout) {
+        let topology = layout.build().unwrap_or_else(|error| panic!("{:?}: {}", layout, error));
+        let name = format!("{} {}x{} {:?} {} floors", layout.kind, layout.rows, layout.columns, layout.wrap, layout.floors);
+        let mut slots = HashMap::<usize, HashSet<(CellId, Direction)>>::new();
+        for cell in (0..topology.cell_count()).filter(|cell| topology.is_included(*cell)) {
+            for direction in 0..topology.direction_count(cell) {
+                let at = format!("{}: cell {} side {}", name, cell, direction);
+                let slot = topology.wall_index(cell, direction);
+                let neighbour = match topology.neighbour(cell, direction) {
+                    Some(neighbour) => neighbour,
+                    None => {
+                        assert_eq!(slot, None, "{}", at);
+                        continue;
+                    }
+                };
+                assert!(neighbour < topology.cell_count() && topology.is_included(neighbour), "{}", at);
+                let back = topology.opposite(cell, direction);
+                assert_eq!(topology.neighbour(neighbour, back), Some(cell), "{}", at);
+                let slot = slot.unwrap_or_else(|| panic!("{}: no slot", at));
+                assert!(slot < topology.wall_count(), "{}", at);
+                assert_eq!(topology.wall_index(neighbour, back), Some(slot), "{}", at);
+                let sides = slots.entry(slot).or_default();
+                sides.insert((cell, direction));
+                sides.insert((neighbour, back));
+                assert_eq!(sides.len(), 2, "{}: slot {} is shared by {:?}", at, slot, sides);
+                if let Some(across) = topology.straight_on(cell, direction) {
+                    assert_eq!(topology.straight_on(cell, across), Some(direction), "{}", at);
+                }
+            }
+        }
+        for side in Side::ALL {
+            for (cell, direction) in topology.border(side) {
+                assert!(topology.is_included(cell) && topology.neighbour(cell, direction).is_none(), "{}: border {:?}", name, side);
+            }
+        }
+    }
+
+    fn layouts(kind: TopologyKind, sizes: &[[u32; 2]]) -> Vec<Layout> {
+        let mask = |rows: u32, columns: u32| {
+            (0..rows).map(|row| (0..columns).map(|column| if (row + column * 2) % 5 == 4 { 'X' } else { '.' }).collect()).collect()
+        };
+        let mut layouts = Vec::new();
+        for &[rows, columns] in sizes {
+            layouts.push(Layout::new(kind, rows, columns));
+            for floors in [2, 3] {
+                layouts.push(Layout { floors, ..Layout::new(kind, rows, columns) });
+            }
+            if kind != TopologyKind::Polar {
+                layouts.push(Layout { mask: Some(mask(rows, columns)), ..Layout::new(kind, rows, columns) });
+            }
+        }
+        layouts
+    }
+
+    #[test]
+    fn rectangular_walls_match_from_both_sides() {
+        for layout in layouts(TopologyKind::Rectangular, &[[1, 1], [1, 5], [4, 1], [5, 7], [6, 6]]) {
+            check(&layout);
+        }
+        for wrap in [Wrap::Cylinder, Wrap::Torus, Wrap::Mobius, Wrap::Klein] {
+            for [rows, columns] in [[3, 3], [4, 5], [5, 4], [7, 6]] {
+                for floors in [1, 2] {
+                    check(&Layout { wrap, floors, ..Layout::new(TopologyKind::Rectangular, rows, columns) });
+                }
+            }
+            let wraps_rows = matches!(wrap, Wrap::Torus | Wrap::Klein);
+            for [rows, columns] in [[1, 1], [1, 4], [4, 1], [2, 2]] {
+                let layout = Layout { wrap, ..Layout::new(TopologyKind::Rectangular, rows, columns) };
+                let rejected = columns < 2 || (wraps_rows && rows < 2);
+                assert_eq!(layout.build().is_err(), rejected, "{:?} {}x{}", wrap, rows, columns);
+                if !rejected {
+                    check(&layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hexagonal_walls_match_from_both_sides() {
+        for layout in layouts(TopologyKind::Hexagonal, &[[1, 1], [1, 4], [5, 1], [4, 5], [5, 6]]) {
+            check(&layout);
+        }
+    }
+
+    #[test]
+    fn triangular_walls_match_from_both_sides() {
+        for layout in layouts(TopologyKind::Triangular, &[[1, 1], [1, 5], [3, 2], [4, 5], [5, 8]]) {
+            check(&layout);
+        }
+    }
+
+    #[test]
+    fn polar_walls_match_from_both_sides() {
+        for layout in layouts(TopologyKind::Polar, &[[1, 1], [2, 1], [5, 1], [9, 1]]) {
+            check(&layout);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use super::{CellId, Direction, Layout, Side, Topology, TopologyKind, Vector2D};
+
+pub const NORTH_EAST: Direction = 0;
+pub const EAST: Direction = 1;
+pub const SOUTH_EAST: Direction = 2;
+pub const SOUTH_WEST: Direction = 3;
+pub const WEST: Direction = 4;
+pub const NORTH_WEST: Direction = 5;
+
+const HALF_SQRT_3: f32 = 0.866_025_4;
+
+/// Pointy-topped hexagons in `rows` rows of `columns` cells, every odd row shifted half a cell
+/// to the right. Cells are one unit wide; directions run clockwise from north-east.
+#[derive(Clone)]
+pub struct Hexagonal {
+    rows: u32,
+    columns: u32
+}
+
+impl Hexagonal {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self {
+            rows,
+            columns
+        }
+    }
+
+    fn cell(&self, row: u32, column: u32) -> CellId {
+        row as usize * self.columns as usize + column as usize
+    }
+
+    fn location(&self, cell: CellId) -> [u32; 2] {
+        [(cell / self.columns as usize) as u32, (cell % self.columns as usize) as u32]
+    }
+
+    fn radius() -> f32 {
+        0.5 / HALF_SQRT_3
+    }
+}
+
+impl Topology for Hexagonal {
+    fn layout(&self) -> Layout {
+        Layout::new(TopologyKind::Hexagonal, self.rows, self.columns)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.rows as usize * self.columns as usize
+    }
+
+    fn direction_count(&self, _cell: CellId) -> usize {
+        6
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        let [row, column] = self.location(cell);
+        let (row, column) = (row as i64, column as i64);
+        let shift = row % 2;
+        let (next_row, next_column) = match direction {
+            NORTH_EAST => (row - 1, column + shift),
+            EAST => (row, column + 1),
+            SOUTH_EAST => (row + 1, column + shift),
+            SOUTH_WEST => (row + 1, column + shift - 1),
+            WEST => (row, column - 1),
+            NORTH_WEST => (row - 1, column + shift - 1),
+            _ => return None
+        };
+        if next_row < 0 || next_row >= self.rows as i64 || next_column < 0 || next_column >= self.columns as i64 {
+            return None;
+        }
+        Some(self.cell(next_row as u32, next_column as u32))
+    }
+
+    fn wall_count(&self) -> usize {
+        self.cell_count() * 3
+    }
+
+    /// Each cell owns the walls east, south-east and south-west of it.
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        let neighbour = self.neighbour(cell, direction)?;
+        match direction {
+            EAST | SOUTH_EAST | SOUTH_WEST => Some(cell * 3 + direction - EAST),
+            _ => Some(neighbour * 3 + self.opposite(cell, direction) - EAST)
+        }
+    }
+
+    fn opposite(&self, _cell: CellId, direction: Direction) -> Direction {
+        (direction + 3) % 6
+    }
+
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        match side {
+            Side::Top => (0..self.columns).map(|column| (self.cell(0, column), NORTH_EAST)).collect(),
+            Side::Right => (0..self.rows).map(|row| (self.cell(row, self.columns - 1), EAST)).collect(),
+            Side::Bottom => (0..self.columns).map(|column| (self.cell(self.rows - 1, column), SOUTH_WEST)).collect(),
+            Side::Left => (0..self.rows).map(|row| (self.cell(row, 0), WEST)).collect()
+        }
+    }
+
+    fn size(&self) -> Vector2D {
+        let shift = if self.rows > 1 { 0.5 } else { 0.0 };
+        Vector2D::new(self.columns as f32 + shift, Self::radius() * (2.0 + 1.5 * (self.rows as f32 - 1.0)))
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        let [row, column] = self.location(cell);
+        Vector2D::new(
+            column as f32 + 0.5 + (row % 2) as f32 * 0.5,
+            Self::radius() * (1.0 + 1.5 * row as f32)
+        )
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        let center = self.cell_center(cell);
+        let radius = Self::radius();
+        [(0.0, -1.0), (HALF_SQRT_3, -0.5), (HALF_SQRT_3, 0.5), (0.0, 1.0), (-HALF_SQRT_3, 0.5), (-HALF_SQRT_3, -0.5)]
+            .iter()
+            .map(|(x, y)| Vector2D::new(center.x + x * radius, center.y + y * radius))
+            .collect()
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        let corners = self.cell_polygon(cell);
+        vec![corners[direction], corners[(direction + 1) % 6]]
+    }
+}
@@ -0,0 +1,196 @@
+use super::{CellId, Direction, Layout, Side, Stairs, Topology, Vector2D};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const FLOOR_GAP: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FloorView {
+    Single,
+    #[default]
+    SideBySide
+}
+
+impl FromStr for FloorView {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim() {
+            "single" => Ok(FloorView::Single),
+            "side_by_side" => Ok(FloorView::SideBySide),
+            other => Err(format!("Unknown floor view \"{}\", expected \"single\" or \"side_by_side\"", other))
+        }
+    }
+}
+
+/// A stack of identical floors of some other topology. Every cell keeps the sides it has on its
+/// own floor and gets two more after them: stairs up and stairs down. Side by side, the floors are
+/// laid out in a roughly square block; in single view they share the same spot and renderers
+/// show one at a time.
+#[derive(Clone)]
+pub struct Layered {
+    floor: Box<dyn Topology>,
+    floors: u8,
+    view: FloorView
+}
+
+impl Layered {
+    pub fn new(floor: Box<dyn Topology>, floors: u8, view: FloorView) -> Self {
+        Self {
+            floor,
+            floors,
+            view
+        }
+    }
+
+    fn location(&self, cell: CellId) -> (usize, CellId) {
+        (cell / self.floor.cell_count(), cell % self.floor.cell_count())
+    }
+
+    fn floors_per_row(&self) -> usize {
+        (self.floors as f32).sqrt().ceil() as usize
+    }
+
+    fn offset(&self, floor: usize) -> Vector2D {
+        if self.view == FloorView::Single {
+            return Vector2D::new(0.0, 0.0);
+        }
+        let size = self.floor.size();
+        let per_row = self.floors_per_row();
+        Vector2D::new(
+            (floor % per_row) as f32 * (size.x + FLOOR_GAP),
+            (floor / per_row) as f32 * (size.y + FLOOR_GAP)
+        )
+    }
+
+    fn moved(&self, floor: usize, points: Vec<Vector2D>) -> Vec<Vector2D> {
+        let offset = self.offset(floor);
+        points.into_iter().map(|point| Vector2D::new(point.x + offset.x, point.y + offset.y)).collect()
+    }
+}
+
+impl Topology for Layered {
+    fn layout(&self) -> Layout {
+        Layout {
+            floors: self.floors,
+            floor_view: self.view,
+            ..self.floor.layout()
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.floor.cell_count() * self.floors as usize
+    }
+
+    fn is_included(&self, cell: CellId) -> bool {
+        self.floor.is_included(self.location(cell).1)
+    }
+
+    fn direction_count(&self, cell: CellId) -> usize {
+        self.floor.direction_count(self.location(cell).1) + 2
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        let (floor, floor_cell) = self.location(cell);
+        let flat_directions = self.floor.direction_count(floor_cell);
+        if direction < flat_directions {
+            return self.floor.neighbour(floor_cell, direction).map(|neighbour| cell - floor_cell + neighbour);
+        }
+        match direction - flat_directions {
+            0 if floor + 1 < self.floors as usize => Some(cell + self.floor.cell_count()),
+            1 if floor > 0 => Some(cell - self.floor.cell_count()),
+            _ => None
+        }
+    }
+
+    /// Every floor keeps its own walls, followed by one slot per cell for the stairs up from it.
+    fn wall_count(&self) -> usize {
+        self.floor.wall_count() * self.floors as usize + self.cell_count()
+    }
+
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        let neighbour = self.neighbour(cell, direction)?;
+        let (floor, floor_cell) = self.location(cell);
+        match self.stairs(cell, direction) {
+            None => self.floor.wall_index(floor_cell, direction).map(|index| floor * self.floor.wall_count() + index),
+            Some(stairs) => Some(self.floor.wall_count() * self.floors as usize + if stairs == Stairs::Up { cell } else { neighbour })
+        }
+    }
+
+    fn opposite(&self, cell: CellId, direction: Direction) -> Direction {
+        let (_, floor_cell) = self.location(cell);
+        let flat_directions = self.floor.direction_count(floor_cell);
+        match direction.checked_sub(flat_directions) {
+            Some(0) => flat_directions + 1,
+            Some(_) => flat_directions,
+            None => self.floor.opposite(floor_cell, direction)
+        }
+    }
+
+    fn stairs(&self, cell: CellId, direction: Direction) -> Option<Stairs> {
+        match direction.checked_sub(self.floor.direction_count(self.location(cell).1)) {
+            Some(0) => Some(Stairs::Up),
+            Some(_) => Some(Stairs::Down),
+            None => None
+        }
+    }
+
+    fn wrap_marker(&self, cell: CellId, direction: Direction) -> Option<Vec<Vector2D>> {
+        let (floor, floor_cell) = self.location(cell);
+        if direction >= self.floor.direction_count(floor_cell) {
+            return None;
+        }
+        self.floor.wrap_marker(floor_cell, direction).map(|marker| self.moved(floor, marker))
+    }
+
+    fn floor_count(&self) -> usize {
+        self.floors as usize
+    }
+
+    fn floor(&self, cell: CellId) -> usize {
+        self.location(cell).0
+    }
+
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        let floor_border = self.floor.border(side);
+        (0..self.floors as usize)
+            .flat_map(|floor| floor_border.iter().map(move |(cell, direction)| (floor * self.floor.cell_count() + cell, *direction)))
+            .collect()
+    }
+
+    fn size(&self) -> Vector2D {
+        let size = self.floor.size();
+        if self.view == FloorView::Single {
+            return size;
+        }
+        let per_row = self.floors_per_row();
+        let rows = (self.floors as usize).div_ceil(per_row);
+        Vector2D::new(
+            per_row as f32 * (size.x + FLOOR_GAP) - FLOOR_GAP,
+            rows as f32 * (size.y + FLOOR_GAP) - FLOOR_GAP
+        )
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        let (floor, floor_cell) = self.location(cell);
+        self.moved(floor, vec![self.floor.cell_center(floor_cell)])[0]
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        let (floor, floor_cell) = self.location(cell);
+        self.moved(floor, self.floor.cell_polygon(floor_cell))
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        let (floor, floor_cell) = self.location(cell);
+        if direction >= self.floor.direction_count(floor_cell) {
+            return Vec::new();
+        }
+        self.moved(floor, self.floor.wall(floor_cell, direction))
+    }
+}
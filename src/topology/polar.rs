@@ -0,0 +1,194 @@
+use super::{CellId, Direction, Layout, Side, Topology, TopologyKind, Vector2D};
+use std::f32::consts::PI;
+
+pub const CLOCKWISE: Direction = 0;
+pub const COUNTER_CLOCKWISE: Direction = 1;
+pub const INWARD: Direction = 2;
+pub const OUTWARD: Direction = 3;
+
+/// Concentric rings of unit thickness around a single center cell. A ring splits each of its
+/// cells into several in the next ring out whenever the cells would otherwise get too wide,
+/// so cells have a varying number of sides: clockwise, counter-clockwise, inward and one
+/// `OUTWARD + k` for every cell they touch in the next ring. The center cell only has the
+/// outward sides, numbered from 0.
+#[derive(Clone)]
+pub struct Polar {
+    ring_counts: Vec<usize>,
+    ring_offsets: Vec<usize>
+}
+
+impl Polar {
+    pub fn new(rings: u32) -> Self {
+        let mut ring_counts = vec![1];
+        for ring in 1..rings as usize {
+            let previous = ring_counts[ring - 1];
+            let cell_width = 2.0 * PI * ring as f32 / previous as f32;
+            ring_counts.push(previous * (cell_width.round() as usize).max(1));
+        }
+        let ring_offsets = ring_counts.iter()
+            .scan(0, |offset, count| {
+                let start = *offset;
+                *offset += count;
+                Some(start)
+            })
+            .collect();
+        Self {
+            ring_counts,
+            ring_offsets
+        }
+    }
+
+    fn rings(&self) -> usize {
+        self.ring_counts.len()
+    }
+
+    fn cell(&self, ring: usize, index: usize) -> CellId {
+        self.ring_offsets[ring] + index
+    }
+
+    fn location(&self, cell: CellId) -> (usize, usize) {
+        let ring = match self.ring_offsets.binary_search(&cell) {
+            Ok(ring) => ring,
+            Err(ring) => ring - 1
+        };
+        (ring, cell - self.ring_offsets[ring])
+    }
+
+    fn outward_count(&self, ring: usize) -> usize {
+        if ring + 1 < self.rings() {
+            self.ring_counts[ring + 1] / self.ring_counts[ring]
+        } else {
+            1
+        }
+    }
+
+    fn outward_base(ring: usize) -> Direction {
+        if ring == 0 { 0 } else { OUTWARD }
+    }
+
+    fn angles(&self, ring: usize, index: usize) -> (f32, f32) {
+        let step = 2.0 * PI / self.ring_counts[ring] as f32;
+        (index as f32 * step, (index + 1) as f32 * step)
+    }
+
+    fn point(&self, radius: f32, angle: f32) -> Vector2D {
+        let center = self.rings() as f32;
+        Vector2D::new(center + radius * angle.cos(), center + radius * angle.sin())
+    }
+
+    fn arc(&self, radius: f32, from: f32, to: f32) -> Vec<Vector2D> {
+        let segments = ((to - from).abs() * radius * 4.0).ceil().max(1.0) as usize;
+        (0..=segments)
+            .map(|segment| self.point(radius, from + (to - from) * segment as f32 / segments as f32))
+            .collect()
+    }
+}
+
+impl Topology for Polar {
+    fn layout(&self) -> Layout {
+        Layout::new(TopologyKind::Polar, self.rings() as u32, 1)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.ring_offsets[self.rings() - 1] + self.ring_counts[self.rings() - 1]
+    }
+
+    fn direction_count(&self, cell: CellId) -> usize {
+        let (ring, _) = self.location(cell);
+        Self::outward_base(ring) + self.outward_count(ring)
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        let (ring, index) = self.location(cell);
+        let count = self.ring_counts[ring];
+        let outward_base = Self::outward_base(ring);
+        match direction {
+            CLOCKWISE if ring != 0 => Some(self.cell(ring, (index + 1) % count)),
+            COUNTER_CLOCKWISE if ring != 0 => Some(self.cell(ring, (index + count - 1) % count)),
+            INWARD if ring != 0 => Some(self.cell(ring - 1, index / (count / self.ring_counts[ring - 1]))),
+            _ if direction >= outward_base && direction < outward_base + self.outward_count(ring) && ring + 1 < self.rings() => {
+                Some(self.cell(ring + 1, index * self.outward_count(ring) + direction - outward_base))
+            },
+            _ => None
+        }
+    }
+
+    fn opposite(&self, cell: CellId, direction: Direction) -> Direction {
+        let (ring, _) = self.location(cell);
+        match direction {
+            CLOCKWISE if ring != 0 => COUNTER_CLOCKWISE,
+            COUNTER_CLOCKWISE if ring != 0 => CLOCKWISE,
+            INWARD if ring != 0 => {
+                let (_, index) = self.location(self.neighbour(cell, direction).unwrap());
+                let (_, own_index) = self.location(cell);
+                Self::outward_base(ring - 1) + own_index - index * self.outward_count(ring - 1)
+            },
+            _ => INWARD
+        }
+    }
+
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        let ring = self.rings() - 1;
+        let quarter = match side {
+            Side::Right => 0,
+            Side::Bottom => 1,
+            Side::Left => 2,
+            Side::Top => 3
+        };
+        (0..self.ring_counts[ring])
+            .filter(|index| {
+                let (from, to) = self.angles(ring, *index);
+                let middle = ((from + to) / 2.0 + PI / 4.0) % (2.0 * PI);
+                (middle / (PI / 2.0)) as usize == quarter
+            })
+            .map(|index| (self.cell(ring, index), Self::outward_base(ring)))
+            .collect()
+    }
+
+    fn size(&self) -> Vector2D {
+        Vector2D::new(2.0 * self.rings() as f32, 2.0 * self.rings() as f32)
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        let (ring, index) = self.location(cell);
+        if ring == 0 {
+            return self.point(0.0, 0.0);
+        }
+        let (from, to) = self.angles(ring, index);
+        self.point(ring as f32 + 0.5, (from + to) / 2.0)
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        let (ring, index) = self.location(cell);
+        if ring == 0 {
+            let mut circle = self.arc(1.0, 0.0, 2.0 * PI);
+            circle.pop();
+            return circle;
+        }
+        let (from, to) = self.angles(ring, index);
+        let mut polygon = self.arc(ring as f32, from, to);
+        polygon.extend(self.arc(ring as f32 + 1.0, to, from));
+        polygon
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        let (ring, index) = self.location(cell);
+        let (from, to) = self.angles(ring, index);
+        let (inner, outer) = (ring as f32, ring as f32 + 1.0);
+        match direction {
+            CLOCKWISE if ring != 0 => vec![self.point(inner, to), self.point(outer, to)],
+            COUNTER_CLOCKWISE if ring != 0 => vec![self.point(inner, from), self.point(outer, from)],
+            INWARD if ring != 0 => self.arc(inner, from, to),
+            _ => {
+                let outward_count = self.outward_count(ring) as f32;
+                let k = (direction - Self::outward_base(ring)) as f32;
+                let step = (to - from) / outward_count;
+                self.arc(outer, from + k * step, from + (k + 1.0) * step)
+            }
+        }
+    }
+}
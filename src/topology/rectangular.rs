@@ -0,0 +1,215 @@
+use super::{CellId, Direction, Layout, Side, Topology, TopologyKind, Vector2D};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub const UP: Direction = 0;
+pub const RIGHT: Direction = 1;
+pub const DOWN: Direction = 2;
+pub const LEFT: Direction = 3;
+
+/// Which edges of the grid are glued together. Leaving through a glued edge re-enters on the
+/// opposite one, upside down when the gluing flips.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    #[default]
+    None,
+    Cylinder,
+    Torus,
+    Mobius,
+    Klein
+}
+
+impl Wrap {
+    /// Whether the left and right edges are glued, and if so whether they flip.
+    fn horizontal(&self) -> Option<bool> {
+        match self {
+            Wrap::None => None,
+            Wrap::Cylinder | Wrap::Torus => Some(false),
+            Wrap::Mobius | Wrap::Klein => Some(true)
+        }
+    }
+
+    /// Whether the top and bottom edges are glued, and if so whether they flip.
+    fn vertical(&self) -> Option<bool> {
+        match self {
+            Wrap::Torus | Wrap::Klein => Some(false),
+            _ => None
+        }
+    }
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim() {
+            "none" => Ok(Wrap::None),
+            "cylinder" => Ok(Wrap::Cylinder),
+            "torus" => Ok(Wrap::Torus),
+            "mobius" => Ok(Wrap::Mobius),
+            "klein" => Ok(Wrap::Klein),
+            other => Err(format!("Unknown wrap \"{}\", expected none, cylinder, torus, mobius or klein", other))
+        }
+    }
+}
+
+/// The classic grid of square cells, `rows` by `columns`, with directions up, right, down, left.
+#[derive(Clone)]
+pub struct Rectangular {
+    rows: u32,
+    columns: u32,
+    wrap: Wrap
+}
+
+impl Rectangular {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self::with_wrap(rows, columns, Wrap::None)
+    }
+
+    pub fn with_wrap(rows: u32, columns: u32, wrap: Wrap) -> Self {
+        Self {
+            rows,
+            columns,
+            wrap
+        }
+    }
+
+    fn cell(&self, row: u32, column: u32) -> CellId {
+        row as usize * self.columns as usize + column as usize
+    }
+
+    fn location(&self, cell: CellId) -> [u32; 2] {
+        [(cell / self.columns as usize) as u32, (cell % self.columns as usize) as u32]
+    }
+
+    fn crosses_edge(&self, cell: CellId, direction: Direction) -> bool {
+        let [row, column] = self.location(cell);
+        match direction {
+            UP => row == 0,
+            RIGHT => column == self.columns - 1,
+            DOWN => row == self.rows - 1,
+            _ => column == 0
+        }
+    }
+}
+
+impl Topology for Rectangular {
+    fn layout(&self) -> Layout {
+        Layout {
+            wrap: self.wrap,
+            ..Layout::new(TopologyKind::Rectangular, self.rows, self.columns)
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.rows as usize * self.columns as usize
+    }
+
+    fn direction_count(&self, _cell: CellId) -> usize {
+        4
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        let [row, column] = self.location(cell);
+        let flipped_row = self.rows - 1 - row;
+        let flipped_column = self.columns - 1 - column;
+        match direction {
+            UP if row != 0 => Some(self.cell(row - 1, column)),
+            RIGHT if column != self.columns - 1 => Some(self.cell(row, column + 1)),
+            DOWN if row != self.rows - 1 => Some(self.cell(row + 1, column)),
+            LEFT if column != 0 => Some(self.cell(row, column - 1)),
+            UP => self.wrap.vertical().map(|flip| self.cell(self.rows - 1, if flip { flipped_column } else { column })),
+            RIGHT => self.wrap.horizontal().map(|flip| self.cell(if flip { flipped_row } else { row }, 0)),
+            DOWN => self.wrap.vertical().map(|flip| self.cell(0, if flip { flipped_column } else { column })),
+            LEFT => self.wrap.horizontal().map(|flip| self.cell(if flip { flipped_row } else { row }, self.columns - 1)),
+            _ => None
+        }
+    }
+
+    fn wall_count(&self) -> usize {
+        self.cell_count() * 2
+    }
+
+    /// Each cell owns the walls on its right and below it.
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        let neighbour = self.neighbour(cell, direction)?;
+        match direction {
+            RIGHT => Some(cell * 2),
+            DOWN => Some(cell * 2 + 1),
+            LEFT => Some(neighbour * 2),
+            _ => Some(neighbour * 2 + 1)
+        }
+    }
+
+    fn opposite(&self, _cell: CellId, direction: Direction) -> Direction {
+        (direction + 2) % 4
+    }
+
+    fn straight_on(&self, _cell: CellId, direction: Direction) -> Option<Direction> {
+        Some((direction + 2) % 4)
+    }
+
+    fn grid(&self) -> Option<[u32; 2]> {
+        (self.wrap == Wrap::None).then_some([self.rows, self.columns])
+    }
+
+    fn wrap_marker(&self, cell: CellId, direction: Direction) -> Option<Vec<Vector2D>> {
+        if !self.crosses_edge(cell, direction) {
+            return None;
+        }
+        let flip = match direction {
+            UP | DOWN => self.wrap.vertical()?,
+            _ => self.wrap.horizontal()?
+        };
+        let wall = self.wall(cell, direction);
+        let middle = Vector2D::new((wall[0].x + wall[1].x) / 2.0, (wall[0].y + wall[1].y) / 2.0);
+        let reversed = flip && (direction == RIGHT || direction == DOWN);
+        let along = match (direction, reversed) {
+            (UP, false) | (DOWN, false) => Vector2D::new(1.0, 0.0),
+            (UP, true) | (DOWN, true) => Vector2D::new(-1.0, 0.0),
+            (_, false) => Vector2D::new(0.0, 1.0),
+            (_, true) => Vector2D::new(0.0, -1.0)
+        };
+        let across = Vector2D::new(along.y, along.x);
+        Some(vec![
+            Vector2D::new(middle.x + along.x * 0.2, middle.y + along.y * 0.2),
+            Vector2D::new(middle.x - along.x * 0.1 + across.x * 0.12, middle.y - along.y * 0.1 + across.y * 0.12),
+            Vector2D::new(middle.x - along.x * 0.1 - across.x * 0.12, middle.y - along.y * 0.1 - across.y * 0.12)
+        ])
+    }
+
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        match side {
+            Side::Top if self.wrap.vertical().is_none() => (0..self.columns).map(|column| (self.cell(0, column), UP)).collect(),
+            Side::Right if self.wrap.horizontal().is_none() => (0..self.rows).map(|row| (self.cell(row, self.columns - 1), RIGHT)).collect(),
+            Side::Bottom if self.wrap.vertical().is_none() => (0..self.columns).map(|column| (self.cell(self.rows - 1, column), DOWN)).collect(),
+            Side::Left if self.wrap.horizontal().is_none() => (0..self.rows).map(|row| (self.cell(row, 0), LEFT)).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    fn size(&self) -> Vector2D {
+        Vector2D::new(self.columns as f32, self.rows as f32)
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        let [row, column] = self.location(cell);
+        Vector2D::new(column as f32 + 0.5, row as f32 + 0.5)
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        let [row, column] = self.location(cell);
+        let (x, y) = (column as f32, row as f32);
+        vec![Vector2D::new(x, y), Vector2D::new(x + 1.0, y), Vector2D::new(x + 1.0, y + 1.0), Vector2D::new(x, y + 1.0)]
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        let corners = self.cell_polygon(cell);
+        vec![corners[direction], corners[(direction + 1) % 4]]
+    }
+}
@@ -0,0 +1,136 @@
+use super::{CellId, Direction, Layout, Side, Topology, TopologyKind, Vector2D};
+
+pub const LEFT: Direction = 0;
+pub const RIGHT: Direction = 1;
+pub const VERTICAL: Direction = 2;
+
+const HEIGHT: f32 = 0.866_025_4;
+
+/// Rows of alternating up- and down-pointing triangles with unit sides. The cell in row 0,
+/// column 0 points up. Besides its left and right neighbours, an upward triangle touches the
+/// cell below its base and a downward one the cell above its top edge.
+#[derive(Clone)]
+pub struct Triangular {
+    rows: u32,
+    columns: u32
+}
+
+impl Triangular {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self {
+            rows,
+            columns
+        }
+    }
+
+    fn cell(&self, row: u32, column: u32) -> CellId {
+        row as usize * self.columns as usize + column as usize
+    }
+
+    fn location(&self, cell: CellId) -> [u32; 2] {
+        [(cell / self.columns as usize) as u32, (cell % self.columns as usize) as u32]
+    }
+
+    fn points_up(&self, cell: CellId) -> bool {
+        let [row, column] = self.location(cell);
+        (row as usize + column as usize).is_multiple_of(2)
+    }
+}
+
+impl Topology for Triangular {
+    fn layout(&self) -> Layout {
+        Layout::new(TopologyKind::Triangular, self.rows, self.columns)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+
+    fn cell_count(&self) -> usize {
+        self.rows as usize * self.columns as usize
+    }
+
+    fn direction_count(&self, _cell: CellId) -> usize {
+        3
+    }
+
+    fn neighbour(&self, cell: CellId, direction: Direction) -> Option<CellId> {
+        let [row, column] = self.location(cell);
+        match direction {
+            LEFT if column != 0 => Some(self.cell(row, column - 1)),
+            RIGHT if column != self.columns - 1 => Some(self.cell(row, column + 1)),
+            VERTICAL if self.points_up(cell) && row != self.rows - 1 => Some(self.cell(row + 1, column)),
+            VERTICAL if !self.points_up(cell) && row != 0 => Some(self.cell(row - 1, column)),
+            _ => None
+        }
+    }
+
+    fn wall_count(&self) -> usize {
+        self.cell_count() * 2
+    }
+
+    /// Each cell owns the wall on its right, and upward triangles own the one along their base.
+    fn wall_index(&self, cell: CellId, direction: Direction) -> Option<usize> {
+        let neighbour = self.neighbour(cell, direction)?;
+        match direction {
+            RIGHT => Some(cell * 2),
+            LEFT => Some(neighbour * 2),
+            _ if self.points_up(cell) => Some(cell * 2 + 1),
+            _ => Some(neighbour * 2 + 1)
+        }
+    }
+
+    fn opposite(&self, _cell: CellId, direction: Direction) -> Direction {
+        match direction {
+            LEFT => RIGHT,
+            RIGHT => LEFT,
+            _ => VERTICAL
+        }
+    }
+
+    fn border(&self, side: Side) -> Vec<(CellId, Direction)> {
+        match side {
+            Side::Top => (0..self.columns)
+                .map(|column| self.cell(0, column))
+                .filter(|cell| !self.points_up(*cell))
+                .map(|cell| (cell, VERTICAL))
+                .collect(),
+            Side::Right => (0..self.rows).map(|row| (self.cell(row, self.columns - 1), RIGHT)).collect(),
+            Side::Bottom => (0..self.columns)
+                .map(|column| self.cell(self.rows - 1, column))
+                .filter(|cell| self.points_up(*cell))
+                .map(|cell| (cell, VERTICAL))
+                .collect(),
+            Side::Left => (0..self.rows).map(|row| (self.cell(row, 0), LEFT)).collect()
+        }
+    }
+
+    fn size(&self) -> Vector2D {
+        Vector2D::new((self.columns as f32 + 1.0) * 0.5, self.rows as f32 * HEIGHT)
+    }
+
+    fn cell_center(&self, cell: CellId) -> Vector2D {
+        let [row, column] = self.location(cell);
+        let (x, y) = (column as f32 * 0.5, row as f32 * HEIGHT);
+        if self.points_up(cell) {
+            Vector2D::new(x + 0.5, y + HEIGHT * 2.0 / 3.0)
+        } else {
+            Vector2D::new(x + 0.5, y + HEIGHT / 3.0)
+        }
+    }
+
+    fn cell_polygon(&self, cell: CellId) -> Vec<Vector2D> {
+        let [row, column] = self.location(cell);
+        let (x, y) = (column as f32 * 0.5, row as f32 * HEIGHT);
+        if self.points_up(cell) {
+            vec![Vector2D::new(x, y + HEIGHT), Vector2D::new(x + 0.5, y), Vector2D::new(x + 1.0, y + HEIGHT)]
+        } else {
+            vec![Vector2D::new(x, y), Vector2D::new(x + 0.5, y + HEIGHT), Vector2D::new(x + 1.0, y)]
+        }
+    }
+
+    fn wall(&self, cell: CellId, direction: Direction) -> Vec<Vector2D> {
+        let corners = self.cell_polygon(cell);
+        vec![corners[direction], corners[(direction + 1) % 3]]
+    }
+}
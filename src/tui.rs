@@ -0,0 +1,209 @@
+use crate::batch::Generation;
+use crate::maze::Maze;
+use crate::settings::Settings;
+use crate::solver;
+use crate::terminal;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal as screen};
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::time::{Duration, Instant};
+
+/// The slowest and fastest the animation can be set to, in milliseconds per step.
+const SLOWEST: u64 = 10_000;
+const FASTEST: u64 = 1;
+
+const KEYS: &str = "space pause, n step, +/- speed, q quit";
+
+/// Raw mode and the alternate screen for as long as it lives, restored when dropped even if
+/// the animation panics.
+struct Screen {
+    stdout: Stdout
+}
+
+impl Screen {
+    fn enter() -> Result<Self, String> {
+        screen::enable_raw_mode().map_err(|error| format!("Error switching the terminal to raw mode: {}", error))?;
+        let mut stdout = io::stdout();
+        execute!(stdout, screen::EnterAlternateScreen, cursor::Hide).map_err(|error| format!("Error writing to the terminal: {}", error))?;
+        Ok(Self {
+            stdout
+        })
+    }
+
+    /// Redraws the whole screen: the maze, then one status line.
+    fn show(&mut self, picture: &str, status: &str) -> Result<(), String> {
+        let text = format!("{}\n{}\n", picture, status).replace('\n', "\r\n");
+        queue!(self.stdout, cursor::MoveTo(0, 0), screen::Clear(screen::ClearType::All))
+            .and_then(|_| self.stdout.write_all(text.as_bytes()))
+            .and_then(|_| self.stdout.flush())
+            .map_err(|error| format!("Error writing to the terminal: {}", error))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, cursor::Show, screen::LeaveAlternateScreen);
+        let _ = screen::disable_raw_mode();
+    }
+}
+
+/// How the animation moves on, as the keys pressed so far have set it.
+struct Controls {
+    delay: u64,
+    paused: bool
+}
+
+enum Wait {
+    Next,
+    Quit
+}
+
+/// The next key pressed within `timeout`, if any.
+fn next_key(timeout: Duration) -> Result<Option<KeyEvent>, String> {
+    if !event::poll(timeout).map_err(|error| format!("Error reading the keyboard: {}", error))? {
+        return Ok(None);
+    }
+    match event::read().map_err(|error| format!("Error reading the keyboard: {}", error))? {
+        Event::Key(key) if key.kind != KeyEventKind::Release => Ok(Some(key)),
+        _ => Ok(None)
+    }
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+impl Controls {
+    /// Waits until the next step is due, the delay plus `extra` milliseconds after `shown`, or
+    /// until a single step is asked for while paused, handling the keys pressed meanwhile.
+    fn wait(&mut self, shown: Instant, extra: u64) -> Result<Wait, String> {
+        loop {
+            let left = Duration::from_millis(self.delay + extra).saturating_sub(shown.elapsed());
+            if !self.paused && left.is_zero() {
+                return Ok(Wait::Next);
+            }
+            if let Some(wait) = next_key(if self.paused { Duration::from_millis(100) } else { left })?.and_then(|key| self.press(&key)) {
+                return Ok(wait);
+            }
+        }
+    }
+
+    /// Handles a key: pausing, changing the speed, or what to do next when it quits or steps.
+    fn press(&mut self, key: &KeyEvent) -> Option<Wait> {
+        if is_quit(key) {
+            return Some(Wait::Quit);
+        }
+        match key.code {
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('n') | KeyCode::Right if self.paused => return Some(Wait::Next),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.delay = (self.delay / 2).max(FASTEST),
+            KeyCode::Char('-') => self.delay = (self.delay * 2).min(SLOWEST),
+            _ => ()
+        }
+        None
+    }
+
+    fn status(&self, doing: &str) -> String {
+        let state = if self.paused { String::from("paused") } else { format!("{} ms per step", self.delay) };
+        format!("{} | {} | {}", doing, state, KEYS)
+    }
+}
+
+/// Animates generating the maze `batch::generate` makes from the settings and seed in the
+/// terminal, one step every `refresh_rate_in_miliseconds`, then after
+/// `wait_then_solve_in_miliseconds` walks the way from the entrance to the exit if
+/// `show_solution` is set. The keys pause, step and change the speed. Returns the maze, or
+/// `None` when the animation was quit before the maze was finished.
+pub fn run(settings: &Settings, seed: u64) -> Result<Option<Maze>, String> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(String::from("The terminal animation needs an interactive terminal"));
+    }
+    let mut generation = Generation::new(settings, seed)?;
+    // Fails for mazes the terminal cannot draw before the screen is taken over.
+    terminal::render(generation.maze(), None)?;
+    let mut screen = Screen::enter()?;
+    let mut controls = Controls {
+        delay: settings.refresh_rate_in_miliseconds.clamp(FASTEST, SLOWEST),
+        paused: false
+    };
+
+    let mut steps = 0;
+    let mut shown = Instant::now();
+    screen.show(&terminal::render_generation(generation.maze(), generation.backtracker())?, &controls.status("Generating, step 0"))?;
+    while !generation.is_finished() {
+        if let Wait::Quit = controls.wait(shown, 0)? {
+            return Ok(None);
+        }
+        generation.step();
+        steps += 1;
+        shown = Instant::now();
+        screen.show(&terminal::render_generation(generation.maze(), generation.backtracker())?, &controls.status(&format!("Generating, step {}", steps)))?;
+    }
+
+    let maze = generation.finish();
+    let solution = solver::solve(&maze).filter(|_| settings.show_solution).unwrap_or_default();
+    screen.show(&terminal::render(&maze, None)?, &controls.status(&format!("Generated in {} steps", steps)))?;
+    shown = Instant::now();
+    for length in 1..=solution.len() {
+        let extra = if length == 1 { settings.wait_then_solve_in_miliseconds } else { 0 };
+        if let Wait::Quit = controls.wait(shown, extra)? {
+            return Ok(Some(maze));
+        }
+        shown = Instant::now();
+        screen.show(&terminal::render(&maze, Some(&solution[..length]))?, &controls.status(&format!("Solving, {} of {} cells", length, solution.len())))?;
+    }
+
+    let path = if solution.is_empty() { None } else { Some(&solution[..]) };
+    screen.show(&terminal::render(&maze, path)?, "Done | q quit")?;
+    while !next_key(Duration::from_secs(1))?.is_some_and(|key| is_quit(&key)) {}
+    Ok(Some(maze))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn pauses_and_steps() {
+        let mut controls = Controls { delay: 100, paused: false };
+        assert!(controls.press(&key(KeyCode::Char('n'))).is_none());
+        assert!(controls.press(&key(KeyCode::Char(' '))).is_none());
+        assert!(controls.paused);
+        assert_eq!(controls.status("Generating"), format!("Generating | paused | {}", KEYS));
+        assert!(matches!(controls.press(&key(KeyCode::Char('n'))), Some(Wait::Next)));
+        assert!(matches!(controls.press(&key(KeyCode::Right)), Some(Wait::Next)));
+        assert!(controls.paused);
+        assert!(controls.press(&key(KeyCode::Char('p'))).is_none());
+        assert!(!controls.paused);
+        assert_eq!(controls.status("Generating"), format!("Generating | 100 ms per step | {}", KEYS));
+    }
+
+    #[test]
+    fn changes_speed_within_limits() {
+        let mut controls = Controls { delay: 4, paused: false };
+        for expected in [2, 1, 1] {
+            assert!(controls.press(&key(KeyCode::Char('+'))).is_none());
+            assert_eq!(controls.delay, expected);
+        }
+        controls.delay = 3_000;
+        for expected in [6_000, SLOWEST, SLOWEST] {
+            assert!(controls.press(&key(KeyCode::Char('-'))).is_none());
+            assert_eq!(controls.delay, expected);
+        }
+        controls.press(&key(KeyCode::Char('=')));
+        assert_eq!(controls.delay, SLOWEST / 2);
+    }
+
+    #[test]
+    fn quits() {
+        let mut controls = Controls { delay: 100, paused: true };
+        for quit in [key(KeyCode::Char('q')), key(KeyCode::Esc), KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)] {
+            assert!(matches!(controls.press(&quit), Some(Wait::Quit)));
+        }
+        assert!(controls.press(&key(KeyCode::Char('c'))).is_none());
+    }
+}